@@ -0,0 +1,67 @@
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use tree_calc::{
+    batch::evaluate_columns,
+    calculate::{Variables, calculate_with_variables},
+    compile::compile,
+    parse::parse_term,
+    traits::Constants,
+};
+
+static CONSTANTS: Constants<f64> = Constants::<f64> {
+    e: std::f64::consts::E,
+    pi: std::f64::consts::PI,
+};
+
+/// Formulas as they come up in plots and parameter sweeps.
+const TERMS: [(&str, &str); 3] = [
+    ("polynomial", "3x^4 - 2x^3 + x^2 / 7 - 5x + 1"),
+    (
+        "elementary",
+        "sin(x) * exp(-x / 10) + sqrt(x^2 + 1) * ln(x^2 + 2)",
+    ),
+    ("conditional", "x > 0 && x < 50 ? x * (50 - x) : -(x mod 7)"),
+];
+
+const POINTS: usize = 1_000;
+
+fn bench_evaluate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evaluate");
+    group.throughput(Throughput::Elements(POINTS as u64));
+    let xs: Vec<f64> = (0..POINTS).map(|i| i as f64 / 10.0 - 20.0).collect();
+
+    for (name, term) in TERMS {
+        let tree = parse_term(term, &CONSTANTS).unwrap();
+        let program = compile(&tree).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("tree", name), &tree, |b, tree| {
+            let mut variables = Variables::new();
+            b.iter(|| {
+                for &x in &xs {
+                    variables.insert("x".to_owned(), x);
+                    black_box(calculate_with_variables(tree, &variables).unwrap());
+                }
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("compiled", name),
+            &program,
+            |b, program| {
+                let mut stack = Vec::new();
+                b.iter(|| {
+                    for &x in &xs {
+                        black_box(program.evaluate_with_stack(&[x], &mut stack).unwrap());
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("batch", name), &program, |b, program| {
+            b.iter(|| black_box(evaluate_columns(program, &[&xs], POINTS).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_evaluate);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use tree_calc::{calculate::calculate_result, parse::parse_term, traits::Constants};
+
+/// The system allocator, counting the bytes that are allocated and not yet freed.
+struct Counting;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+static CONSTANTS: Constants<f64> = Constants::<f64> {
+    e: std::f64::consts::E,
+    pi: std::f64::consts::PI,
+};
+
+/// `1 + 1 + ... + 1` leans to one side and is as deep as it is long,
+/// the worst case for a heap layout.
+fn chain(n: usize) -> String {
+    vec!["1"; n].join(" + ")
+}
+
+fn bench_chain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chain");
+    let mut bytes_per_term = vec![];
+    // Compiles the token regex, which stays allocated.
+    parse_term(&chain(2), &CONSTANTS).unwrap();
+    for n in [10, 100, 1_000, 10_000, 100_000] {
+        let term = chain(n);
+
+        // The arena holds one node per operand and operator, the bytes that stay
+        // allocated include the children of every node.
+        let before = LIVE_BYTES.load(Ordering::Relaxed);
+        let tree = parse_term(&term, &CONSTANTS).unwrap();
+        let bytes = LIVE_BYTES.load(Ordering::Relaxed) - before;
+        assert_eq!(2 * n - 1, tree.len());
+        bytes_per_term.push(bytes as f64 / n as f64);
+
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("parse", n), &term, |b, term| {
+            b.iter(|| parse_term(black_box(term), &CONSTANTS).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("calculate", n), &tree, |b, tree| {
+            b.iter(|| calculate_result(black_box(tree)).unwrap())
+        });
+    }
+    group.finish();
+
+    // Spare capacity of the arena can double the bytes per term, deeper trees can't.
+    let least = bytes_per_term.iter().copied().fold(f64::INFINITY, f64::min);
+    let most = bytes_per_term.iter().copied().fold(0.0, f64::max);
+    assert!(
+        most <= 2.5 * least,
+        "memory per term isn't constant: {bytes_per_term:?}"
+    );
+}
+
+criterion_group!(benches, bench_chain);
+criterion_main!(benches);
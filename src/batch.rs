@@ -0,0 +1,256 @@
+//! Evaluates a term for many rows of variable values at once.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    calculate::{CalculationError, apply_function, apply_operator, apply_unary_operator},
+    compile::{Instruction, Program, compile},
+    token::Token,
+    traits::{Numeric, Truth},
+    tree::Tree,
+};
+
+/// Values of the variables by name, one entry per row.
+pub type Columns<T> = HashMap<String, Vec<T>>;
+
+/// Rows that are at the same instruction with the same number of values on the stack.
+struct Group {
+    rows: Vec<usize>,
+    depth: usize,
+}
+
+/// Runs `program` once over all rows. Every instruction is applied to a whole column of the
+/// stack, a conditional jump splits the rows into those that jump and those that don't.
+/// Jumps only go forward, so running the group with the lowest position first joins the
+/// rows again after a branch. Rows whose evaluation fails drop out with their error.
+///
+/// `columns` hold the values of [`Program::variables`] in that order, each one for `rows` rows.
+pub fn evaluate_columns<T: Numeric>(
+    program: &Program<T>,
+    columns: &[&[T]],
+    rows: usize,
+) -> Result<Vec<Result<T, CalculationError>>, CalculationError> {
+    if let Some(missing) = program.variables().get(columns.len()) {
+        return Err(CalculationError::UnknownVariable(missing.clone()));
+    }
+    if columns.iter().any(|column| column.len() != rows) {
+        return Err(CalculationError::MismatchedColumns);
+    }
+
+    let instructions = program.instructions();
+    let mut results: Vec<Result<T, CalculationError>> = (0..rows)
+        .map(|_| Err(CalculationError::MissingOperand(String::new())))
+        .collect();
+    let mut stack: Vec<Vec<T>> = vec![];
+    let mut args: Vec<T> = vec![];
+    let mut pending: BTreeMap<usize, Group> = BTreeMap::new();
+    pending.insert(
+        0,
+        Group {
+            rows: (0..rows).collect(),
+            depth: 0,
+        },
+    );
+
+    while let Some((mut pc, group)) = pending.pop_first() {
+        let Group {
+            mut rows,
+            mut depth,
+        } = group;
+        // Runs the group until it jumps, ends or reaches rows that are waiting there.
+        loop {
+            if rows.is_empty() {
+                break;
+            }
+            let Some(instruction) = instructions.get(pc) else {
+                for &r in &rows {
+                    results[r] = Ok(stack[0][r]);
+                }
+                break;
+            };
+            if stack.len() <= depth {
+                stack.resize_with(depth + 1, || vec![T::default(); results.len()]);
+            }
+            let mut failed = |r: usize, err: CalculationError| {
+                results[r] = Err(err);
+                false
+            };
+            pc += 1;
+            match *instruction {
+                Instruction::Push(v) => {
+                    rows.iter().for_each(|&r| stack[depth][r] = v);
+                    depth += 1;
+                }
+                Instruction::Load(slot) => {
+                    let column = columns[slot];
+                    rows.iter().for_each(|&r| stack[depth][r] = column[r]);
+                    depth += 1;
+                }
+                Instruction::Unary(op) => {
+                    let top = &mut stack[depth - 1];
+                    rows.retain(|&r| match apply_unary_operator(op, top[r]) {
+                        Ok(v) => {
+                            top[r] = v;
+                            true
+                        }
+                        Err(err) => failed(r, err),
+                    });
+                }
+                Instruction::Binary(op) => {
+                    let (left, right) = stack.split_at_mut(depth - 1);
+                    let (a, b) = (&mut left[depth - 2], &right[0]);
+                    rows.retain(|&r| match apply_operator(op, a[r], b[r]) {
+                        Ok(v) => {
+                            a[r] = v;
+                            true
+                        }
+                        Err(err) => failed(r, err),
+                    });
+                    depth -= 1;
+                }
+                Instruction::Call(func) => {
+                    let first = depth - func.arity();
+                    rows.retain(|&r| {
+                        args.clear();
+                        args.extend(stack[first..depth].iter().map(|column| column[r]));
+                        match apply_function(func, &args) {
+                            Ok(v) => {
+                                stack[first][r] = v;
+                                true
+                            }
+                            Err(err) => failed(r, err),
+                        }
+                    });
+                    depth = first + 1;
+                }
+                Instruction::Truth => {
+                    let top = &mut stack[depth - 1];
+                    rows.iter()
+                        .for_each(|&r| top[r] = T::from_bool(top[r].is_true()));
+                }
+                Instruction::Jump(target) => {
+                    wait(&mut pending, target, rows, depth);
+                    break;
+                }
+                Instruction::JumpUnless(target) => {
+                    depth -= 1;
+                    let top = &stack[depth];
+                    let (taken, rest): (Vec<usize>, Vec<usize>) =
+                        rows.iter().partition(|&&r| !top[r].is_true());
+                    wait(&mut pending, target, taken, depth);
+                    rows = rest;
+                }
+            }
+            if pending.contains_key(&pc) {
+                wait(&mut pending, pc, rows, depth);
+                break;
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Adds rows to those waiting at instruction `pc`.
+fn wait(pending: &mut BTreeMap<usize, Group>, pc: usize, mut rows: Vec<usize>, depth: usize) {
+    if rows.is_empty() {
+        return;
+    }
+    let group = pending.entry(pc).or_insert(Group {
+        rows: vec![],
+        depth,
+    });
+    debug_assert_eq!(depth, group.depth, "compiled programs are balanced");
+    group.rows.append(&mut rows);
+}
+
+/// Evaluates a term for every row of `columns`, which all need the same length.
+/// Each row gets its own result, so a row that divides by zero doesn't affect the others.
+pub fn calculate_batch<T: Numeric>(
+    term: &Tree<Token<T>>,
+    columns: &Columns<T>,
+) -> Result<Vec<Result<T, CalculationError>>, CalculationError> {
+    let rows = columns.values().next().map_or(0, Vec::len);
+    if columns.values().any(|column| column.len() != rows) {
+        return Err(CalculationError::MismatchedColumns);
+    }
+    let program = compile(term)?;
+    let columns = program
+        .variables()
+        .iter()
+        .map(|name| {
+            columns
+                .get(name)
+                .map(Vec::as_slice)
+                .ok_or_else(|| CalculationError::UnknownVariable(name.clone()))
+        })
+        .collect::<Result<Vec<&[T]>, CalculationError>>()?;
+    evaluate_columns(&program, &columns, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate::{Variables, calculate_with_variables},
+        parse::parse_term,
+        traits::Constants,
+    };
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    #[test]
+    fn test_same_as_rows() {
+        let xs: Vec<f64> = (0..200).map(|i| i as f64 / 8.0 - 12.0).collect();
+        let ys: Vec<f64> = (0..200).map(|i| (i % 7) as f64 - 3.0).collect();
+        let columns = Columns::from([("x".to_owned(), xs.clone()), ("y".to_owned(), ys.clone())]);
+
+        for term in [
+            "x^2 - 3x * y + 1",
+            "y != 0 && x / y > 1 ? ln(x) : sqrt(-x)",
+            "x > 0 || 1 / y < 0",
+            "gcd(y, 6) + nCr(5, 2) - x mod y",
+            "(x > 3 ? y > 0 ? 1 : 2 : 3) * diff(x^3, x)",
+        ] {
+            let tree = parse_term(term, &CONSTANTS).unwrap();
+            let results = calculate_batch(&tree, &columns).unwrap();
+            assert_eq!(xs.len(), results.len());
+            for (i, result) in results.into_iter().enumerate() {
+                let variables = Variables::from([("x".to_owned(), xs[i]), ("y".to_owned(), ys[i])]);
+                assert_eq!(
+                    calculate_with_variables(&tree, &variables),
+                    result,
+                    "{term} in row {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_columns() {
+        let tree = parse_term("1 / x", &CONSTANTS).unwrap();
+        let columns = Columns::from([("x".to_owned(), vec![2.0, 0.0, -4.0])]);
+        assert_eq!(
+            vec![Ok(0.5), Err(CalculationError::DivisionByZero), Ok(-0.25)],
+            calculate_batch(&tree, &columns).unwrap()
+        );
+
+        let columns = Columns::from([("x".to_owned(), vec![1.0]), ("y".to_owned(), vec![])]);
+        assert_eq!(
+            Err(CalculationError::MismatchedColumns),
+            calculate_batch(&tree, &columns)
+        );
+        assert_eq!(
+            Err(CalculationError::UnknownVariable("x".to_owned())),
+            calculate_batch(&tree, &Columns::new())
+        );
+
+        let program = compile(&parse_term("2 + 3", &CONSTANTS).unwrap()).unwrap();
+        assert_eq!(
+            Ok(vec![Ok(5.0), Ok(5.0), Ok(5.0)]),
+            evaluate_columns(&program, &[], 3)
+        );
+    }
+}
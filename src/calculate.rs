@@ -1,42 +1,489 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
 use crate::{
-    token::{Operator, Token},
-    traits::Numeric,
-    tree::BinaryTree,
+    derivative::derivative_at,
+    integrate::{Tolerance, integral_at},
+    token::{Function, Operator, Token},
+    traits::{Division, Numeric, Truth},
+    tree::{NodeId, Tree},
 };
 
-pub fn calculate_result<T: Numeric>(parsed_term: &BinaryTree<Token<T>>) -> T {
-    let mut result: Vec<T> = vec![];
-    parsed_term.traverse_postorder(0, &mut |node| {
-        let (_, el) = node;
-        if el.is_none() {
-            return;
+#[derive(Error, Debug, PartialEq)]
+pub enum CalculationError {
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Result of {0} overflowed")]
+    Overflow(Operator),
+    #[error("{0} is missing an operand")]
+    MissingOperand(String),
+    #[error("Operator {0} can't be evaluated")]
+    InvalidOperator(Operator),
+    #[error("{0} is undefined for the given operands")]
+    Undefined(String),
+    #[error("{0} doesn't evaluate to a single value and has to be the outermost function")]
+    NotAValue(Function),
+    #[error("Variable {0} has no value")]
+    UnknownVariable(String),
+    #[error("{0} can't be differentiated")]
+    NotDifferentiable(String),
+    #[error("Function {0} expects a variable as its argument")]
+    ExpectedVariable(Function),
+    #[error("Integral didn't reach the tolerance, the error estimate is {0:e}")]
+    NotConverged(f64),
+    #[error("Function {0} expects an equation as its first argument")]
+    ExpectedEquation(Function),
+    #[error("Equation has {0} variables, solve(equation, x) names the one to solve for")]
+    AmbiguousEquation(usize),
+    #[error("Every value of {0} solves the equation")]
+    Indeterminate(String),
+    #[error("{0} is not a polynomial")]
+    NotPolynomial(String),
+    #[error("{0} is not a polynomial in one variable with integer coefficients")]
+    NotFactorable(String),
+    #[error("Function {0} can't be compiled")]
+    NotCompilable(Function),
+    #[error("Columns of values have different lengths")]
+    MismatchedColumns,
+    #[error("{0} and {1} have different units")]
+    IncompatibleUnits(String, String),
+    #[error("No exchange rate for {0}")]
+    MissingRate(String),
+    #[error("Exchange rate for {0} from {1} is stale")]
+    StaleRate(String, String),
+}
+
+/// Values bound to the variable names of a term.
+pub type Variables<T> = HashMap<String, T>;
+
+pub(crate) fn apply_unary_operator<T: Numeric>(op: Operator, a: T) -> Result<T, CalculationError> {
+    match op {
+        Operator::NEG => a.checked_neg().ok_or(CalculationError::Overflow(op)),
+        Operator::NOT => Ok(T::from_bool(!a.is_true())),
+        Operator::FAC => a
+            .factorial()
+            .ok_or_else(|| CalculationError::Undefined(format!("{a}!"))),
+        other => Err(CalculationError::InvalidOperator(other)),
+    }
+}
+
+pub(crate) fn apply_function<T: Numeric>(
+    func: Function,
+    args: &[T],
+) -> Result<T, CalculationError> {
+    let undefined = || CalculationError::Undefined(func.to_string());
+
+    match (func, args) {
+        (Function::NCR, &[n, k]) => n.combinations(k).ok_or_else(undefined),
+        (Function::NPR, &[n, k]) => n.permutations(k).ok_or_else(undefined),
+        (Function::GCD, &[a, b]) => a.gcd(b).ok_or_else(undefined),
+        // `a / gcd(a, b) * b` only fails for a defined `gcd` if the product overflows.
+        (Function::LCM, &[a, b]) => a.lcm(b).ok_or_else(|| match a.gcd(b) {
+            Some(_) => CalculationError::Overflow(Operator::MUL),
+            None => undefined(),
+        }),
+        (Function::ISPRIME, &[n]) => n.is_prime().map(T::from_bool).ok_or_else(undefined),
+        (Function::SIN, &[x]) => x.sine().ok_or_else(undefined),
+        (Function::COS, &[x]) => x.cosine().ok_or_else(undefined),
+        (Function::TAN, &[x]) => x.tangent().ok_or_else(undefined),
+        (Function::EXP, &[x]) => x.exponential().ok_or_else(undefined),
+        (Function::LN, &[x]) => x.logarithm().ok_or_else(undefined),
+        (Function::SQRT, &[x]) => x.root().ok_or_else(undefined),
+        // Expanding doesn't change the value.
+        (Function::EXPAND, &[x]) => Ok(x),
+        (Function::FACTOR, _) => Err(CalculationError::NotAValue(func)),
+        _ => Err(CalculationError::MissingOperand(func.to_string())),
+    }
+}
+
+pub(crate) fn apply_operator<T: Numeric>(op: Operator, a: T, b: T) -> Result<T, CalculationError> {
+    let divided = |f: fn(T, T) -> Option<T>| {
+        // Not being greater or less than zero also catches intervals that contain it.
+        if !(b < T::default() || b > T::default()) {
+            return Err(CalculationError::DivisionByZero);
         }
-        // println!("{el:?}");
-        // println!("{result:#?}");
-
-        match el.unwrap() {
-            Token::Op(Operator::ADD) => {
-                let a = result[result.len() - 2];
-                let b = result[result.len() - 1];
-                result.pop();
-                result.pop();
-                result.push(a + b);
+        f(a, b).ok_or(CalculationError::Overflow(op))
+    };
+
+    if !a.defined(op, &b) {
+        return Err(match a.commensurable(&b) {
+            true => CalculationError::Undefined(format!("{a} {op} {b}")),
+            false => CalculationError::IncompatibleUnits(a.to_string(), b.to_string()),
+        });
+    }
+
+    match op {
+        Operator::ADD => a.checked_add(b).ok_or(CalculationError::Overflow(op)),
+        Operator::SUB => a.checked_sub(b).ok_or(CalculationError::Overflow(op)),
+        Operator::MUL => a.checked_mul(b).ok_or(CalculationError::Overflow(op)),
+        Operator::DIV => divided(Division::quotient),
+        Operator::IDIV => divided(Division::floor_div),
+        Operator::MOD => divided(Division::modulo),
+        Operator::REM => divided(Division::remainder),
+        Operator::POW => a
+            .power(b)
+            .ok_or_else(|| CalculationError::Undefined(format!("{a}^{b}"))),
+        Operator::LT => Ok(T::from_bool(a < b)),
+        Operator::LE => Ok(T::from_bool(a <= b)),
+        Operator::GT => Ok(T::from_bool(a > b)),
+        Operator::GE => Ok(T::from_bool(a >= b)),
+        Operator::IS => Ok(T::from_bool(a == b)),
+        Operator::NE => Ok(T::from_bool(a != b)),
+        // The value in multiples of the unit, which the caller shows next to it.
+        Operator::TO => divided(Division::quotient),
+        other => Err(CalculationError::InvalidOperator(other)),
+    }
+}
+
+pub fn calculate_result<T: Numeric>(parsed_term: &Tree<Token<T>>) -> Result<T, CalculationError> {
+    calculate_with_variables(parsed_term, &Variables::new())
+}
+
+pub fn calculate_with_variables<T: Numeric>(
+    parsed_term: &Tree<Token<T>>,
+    variables: &Variables<T>,
+) -> Result<T, CalculationError> {
+    match parsed_term.root() {
+        Some(root) => calculate_subtree(parsed_term, root, variables),
+        None => Ok(T::default()),
+    }
+}
+
+/// Name of the variable at node `k`, which `func` expects as an argument.
+pub(crate) fn variable_name<T: Numeric>(
+    parsed_term: &Tree<Token<T>>,
+    k: NodeId,
+    func: Function,
+) -> Result<&str, CalculationError> {
+    match parsed_term.get(k) {
+        Some(Token::Var(name)) => Ok(name),
+        _ => Err(CalculationError::ExpectedVariable(func)),
+    }
+}
+
+/// Work left for [`calculate_subtree`].
+enum Step {
+    Visit(NodeId),
+    /// Applies the operator or function of the node to its operands on top of the stack.
+    Apply(NodeId),
+    /// Decides on the left operand of `&&` or `||` whether the right one is needed.
+    ShortCircuit(NodeId),
+    /// Replaces the top of the stack with 1 if it is true and 0 otherwise.
+    Truth,
+    /// Evaluates the branch of `?:` that the condition on top of the stack picks.
+    Branch(NodeId),
+}
+
+/// Evaluates the subtree rooted at node `k`. `&&`, `||` and `?:` only evaluate
+/// the operands that decide their result. Works with an explicit stack, so long
+/// chains like `1 + 1 + … + 1` don't overflow the call stack.
+pub(crate) fn calculate_subtree<T: Numeric>(
+    parsed_term: &Tree<Token<T>>,
+    k: NodeId,
+    variables: &Variables<T>,
+) -> Result<T, CalculationError> {
+    let missing = || CalculationError::MissingOperand(String::new());
+    let mut steps = vec![Step::Visit(k)];
+    let mut values: Vec<T> = vec![];
+
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Visit(k) => {
+                let el = parsed_term.get(k).ok_or_else(missing)?;
+                let children = parsed_term.children(k);
+                if children.len() < el.arity() {
+                    return Err(CalculationError::MissingOperand(el.to_string()));
+                }
+                let child = |n: usize| calculate_subtree(parsed_term, children[n], variables);
+
+                match el {
+                    Token::Value(val) => values.push(*val),
+                    Token::Var(name) => values.push(
+                        variables
+                            .get(name)
+                            .copied()
+                            .ok_or_else(|| CalculationError::UnknownVariable(name.clone()))?,
+                    ),
+                    Token::Op(Operator::AND | Operator::OR) => {
+                        steps.extend([Step::ShortCircuit(k), Step::Visit(children[0])])
+                    }
+                    Token::Op(Operator::COND) => {
+                        steps.extend([Step::Branch(k), Step::Visit(children[0])])
+                    }
+                    Token::Func(Function::DIFF) => {
+                        let var = variable_name(parsed_term, children[1], Function::DIFF)?;
+                        let derivative = derivative_at(parsed_term, children[0], var)?;
+                        values.push(calculate_with_variables(&derivative, variables)?);
+                    }
+                    Token::Func(func @ Function::SOLVE) => {
+                        return Err(CalculationError::NotAValue(*func));
+                    }
+                    Token::Func(Function::INTEGRATE) => {
+                        let var = variable_name(parsed_term, children[1], Function::INTEGRATE)?;
+                        let bounds = (child(2)?.to_f64(), child(3)?.to_f64());
+                        let tolerance = Tolerance::for_epsilon(T::epsilon());
+                        let integral = integral_at(
+                            parsed_term,
+                            children[0],
+                            var,
+                            bounds,
+                            variables,
+                            &tolerance,
+                        )?;
+                        if !tolerance.accepts(&integral) {
+                            return Err(CalculationError::NotConverged(integral.error));
+                        }
+                        values.push(T::from_f64(integral.value).ok_or_else(|| {
+                            CalculationError::Undefined(Function::INTEGRATE.to_string())
+                        })?);
+                    }
+                    Token::Op(op) if op.arity() == 0 => {
+                        return Err(CalculationError::InvalidOperator(*op));
+                    }
+                    Token::Op(op) => {
+                        steps.push(Step::Apply(k));
+                        let operands = children[..op.arity()].iter().rev();
+                        steps.extend(operands.map(|&child| Step::Visit(child)));
+                    }
+                    Token::Func(_) => {
+                        steps.push(Step::Apply(k));
+                        steps.extend(children.iter().rev().map(|&child| Step::Visit(child)));
+                    }
+                    Token::Empty => return Err(missing()),
+                }
+            }
+            Step::ShortCircuit(k) => {
+                let left = values.pop().ok_or_else(missing)?.is_true();
+                match (parsed_term.get(k), left) {
+                    (Some(Token::Op(Operator::AND)), false)
+                    | (Some(Token::Op(Operator::OR)), true) => values.push(T::from_bool(left)),
+                    _ => steps.extend([Step::Truth, Step::Visit(parsed_term.children(k)[1])]),
+                }
             }
-            Token::Op(Operator::MUL) => {
-                let a = result[result.len() - 2];
-                let b = result[result.len() - 1];
-                result.pop();
-                result.pop();
-                result.push(a * b);
+            Step::Truth => {
+                let value = values.pop().ok_or_else(missing)?;
+                values.push(T::from_bool(value.is_true()));
             }
-            Token::Value(val) => result.push(*val),
-            Token::Empty => {
-                println!("None");
+            Step::Branch(k) => {
+                let branch = match values.pop().ok_or_else(missing)?.is_true() {
+                    true => 1,
+                    false => 2,
+                };
+                steps.push(Step::Visit(parsed_term.children(k)[branch]));
             }
-            other => println!("Shouldn't be here but is here: {:#?}", other),
+            Step::Apply(k) => {
+                let value = match parsed_term.get(k) {
+                    Some(Token::Op(op)) if op.arity() == 1 => {
+                        apply_unary_operator(*op, values.pop().ok_or_else(missing)?)?
+                    }
+                    Some(Token::Op(op)) => {
+                        let b = values.pop().ok_or_else(missing)?;
+                        let a = values.pop().ok_or_else(missing)?;
+                        apply_operator(*op, a, b)?
+                    }
+                    Some(Token::Func(func)) => {
+                        let arguments = parsed_term.children(k).len();
+                        let start = values.len().checked_sub(arguments).ok_or_else(missing)?;
+                        apply_function(*func, &values.split_off(start))?
+                    }
+                    _ => return Err(missing()),
+                };
+                values.push(value);
+            }
+        }
+    }
+
+    values.pop().ok_or_else(missing)
+}
+
+/// Prime factorization of the argument if the term is a call to `factor`,
+/// `None` for any other term. An argument with variables is a polynomial,
+/// which `simplify` factors instead.
+pub fn factorize_result<T: Numeric>(
+    parsed_term: &Tree<Token<T>>,
+) -> Result<Option<Vec<T>>, CalculationError> {
+    let Some(root) = parsed_term.root() else {
+        return Ok(None);
+    };
+    if parsed_term.get(root) != Some(&Token::Func(Function::FACTOR)) {
+        return Ok(None);
+    }
+    let argument = parsed_term
+        .child(root, 0)
+        .ok_or(CalculationError::MissingOperand(
+            Function::FACTOR.to_string(),
+        ))?;
+    let n = match calculate_subtree(parsed_term, argument, &Variables::new()) {
+        Err(CalculationError::UnknownVariable(_)) => return Ok(None),
+        n => n?,
+    };
+    n.prime_factors()
+        .map(Some)
+        .ok_or(CalculationError::Undefined(format!("factor({n})")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_term, traits::Constants};
+    use std::f32::consts::{E, PI};
+
+    #[test]
+    fn test_division_operators() {
+        let constants = Constants::<f32> { e: E, pi: PI };
+        let calc = |s: &str| calculate_result(&parse_term(s, &constants).unwrap());
+
+        assert_eq!(Ok(-4.0), calc("-7 // 2"));
+        assert_eq!(Ok(1.0), calc("-7 mod 2"));
+        assert_eq!(Ok(-1.0), calc("-7 rem 2"));
+        assert_eq!(Ok(2.0), calc("12 / 3 / 2"));
+        assert_eq!(Ok(12.0), calc("1 + 20 mod 7 * 2 - 6 // 4"));
+        assert_eq!(Err(CalculationError::DivisionByZero), calc("1 mod 0"));
+
+        let int_constants = Constants::<i64> { e: 2, pi: 3 };
+        let calc = |s: &str| calculate_result(&parse_term(s, &int_constants).unwrap());
+
+        assert_eq!(Ok(-3), calc("-7 / 2"));
+        assert_eq!(Ok(-4), calc("-7 // 2"));
+        assert_eq!(Ok(2), calc("(0 - 7) mod 3"));
+        assert_eq!(Ok(-1), calc("-(7) rem 3"));
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let constants = Constants::<i64> { e: 2, pi: 3 };
+        let calc = |s: &str| calculate_result(&parse_term(s, &constants).unwrap());
+
+        assert_eq!(Ok(i64::MAX), calc("9223372036854775806 + 1"));
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::ADD)),
+            calc("9223372036854775807 + 1")
+        );
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::SUB)),
+            calc("0 - 9223372036854775807 - 2")
+        );
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::MUL)),
+            calc("4294967296 * 4294967296")
+        );
+
+        let variables = Variables::from([("x".to_owned(), i64::MIN)]);
+        let term = parse_term("-x", &constants).unwrap();
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::NEG)),
+            calculate_with_variables(&term, &variables)
+        );
+
+        let constants = Constants::<i32> { e: 2, pi: 3 };
+        let term = parse_term("2147483647 + 1", &constants).unwrap();
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::ADD)),
+            calculate_result(&term)
+        );
+    }
+
+    #[test]
+    fn test_logic_operators() {
+        let constants = Constants::<f32> { e: E, pi: PI };
+        let variables = Variables::from([("x".to_owned(), 2.0), ("y".to_owned(), 0.0)]);
+        let calc =
+            |s: &str| calculate_with_variables(&parse_term(s, &constants).unwrap(), &variables);
+
+        assert_eq!(Ok(1.0), calc("3 < 4"));
+        assert_eq!(Ok(0.0), calc("x == 2 && y != 0"));
+        assert_eq!(Ok(1.0), calc("x >= 2 || z"));
+        assert_eq!(Ok(1.0), calc("!y && !(x <= 1)"));
+        assert_eq!(Ok(1.0), calc("1 + 1 == 2 == 1"));
+        assert_eq!(Ok(10.0), calc("x > 1 ? 10 : 1 / y"));
+        assert_eq!(Ok(0.0), calc("y != 0 && 1 / y > 2"));
+        assert_eq!(Ok(5.0), calc("y ? 4 : x < 0 ? 3 : 5"));
+        assert_eq!(
+            Err(CalculationError::UnknownVariable("z".to_owned())),
+            calc("y || z")
+        );
+    }
+
+    #[test]
+    fn test_deep_chain() {
+        let constants = Constants::<f64> { e: 2.0, pi: 3.0 };
+        let sum = vec!["1"; 20_000].join("+");
+        assert_eq!(
+            Ok(20_000.0),
+            calculate_result(&parse_term(&sum, &constants).unwrap())
+        );
+        let power = format!("{}1", "1^".repeat(20_000));
+        assert_eq!(
+            Ok(1.0),
+            calculate_result(&parse_term(&power, &constants).unwrap())
+        );
+        let conditions = format!("{}1", "0 ? 1 : ".repeat(20_000));
+        assert_eq!(
+            Ok(1.0),
+            calculate_result(&parse_term(&conditions, &constants).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_powers_and_functions() {
+        let constants = Constants::<f64> {
+            e: std::f64::consts::E,
+            pi: std::f64::consts::PI,
         };
-    });
+        let calc = |s: &str| calculate_result(&parse_term(s, &constants).unwrap());
 
-    println!("Result: {result:#?}");
-    *result.get(0).unwrap_or(&T::default())
+        assert_eq!(Ok(512.0), calc("2^3^2"));
+        assert_eq!(Ok(-16.0), calc("-2^4"));
+        assert_eq!(Ok(0.5), calc("2^-1"));
+        assert_eq!(Ok(24.0), calc("2^3 * 3"));
+        assert_eq!(Ok(3.0), calc("sqrt(9) + ln(1) + sin(0)"));
+        assert_eq!(Ok(1.0), calc("exp(0) * cos(0)"));
+        assert!(matches!(
+            calc("ln(0 - 1)"),
+            Err(CalculationError::Undefined(_))
+        ));
+
+        let int_constants = Constants::<i64> { e: 2, pi: 3 };
+        let calc = |s: &str| calculate_result(&parse_term(s, &int_constants).unwrap());
+        assert_eq!(Ok(1024), calc("2^10"));
+        assert!(matches!(
+            calc("2^(0 - 1)"),
+            Err(CalculationError::Undefined(_))
+        ));
+    }
+
+    #[test]
+    fn test_number_theory() {
+        let constants = Constants::<i64> { e: 2, pi: 3 };
+        let calc = |s: &str| calculate_result(&parse_term(s, &constants).unwrap());
+
+        assert_eq!(Ok(-6), calc("-3!"));
+        assert_eq!(Ok(343), calc("3!! / 2 - ncr(4, 2) * 99 // 33 + npr(5, 0)"));
+        assert_eq!(Ok(36), calc("lcm(gcd(24, 36), 18)"));
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::MUL)),
+            calc("lcm(0-9223372036854775807-1, 1)")
+        );
+        assert_eq!(Ok(1), calc("isprime(97)"));
+        assert!(matches!(
+            calc("(0 - 1)!"),
+            Err(CalculationError::Undefined(_))
+        ));
+        assert_eq!(
+            Err(CalculationError::NotAValue(Function::FACTOR)),
+            calc("1 + factor(6)")
+        );
+
+        let term = parse_term("factor(2 * 180)", &constants).unwrap();
+        assert_eq!(Ok(Some(vec![2, 2, 2, 3, 3, 5])), factorize_result(&term));
+
+        let float_constants = Constants::<f64> {
+            e: E as f64,
+            pi: PI as f64,
+        };
+        let term = parse_term("0.5!", &float_constants).unwrap();
+        let result = calculate_result(&term).unwrap();
+        assert!((result - 0.886_226_925_452_758).abs() < 1e-12);
+    }
 }
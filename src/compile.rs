@@ -0,0 +1,381 @@
+//! Compiles parsed terms to a flat instruction list for repeated evaluation.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    calculate::{
+        CalculationError, Variables, apply_function, apply_operator, apply_unary_operator,
+        variable_name,
+    },
+    derivative::derivative_at,
+    token::{Function, Operator, Token},
+    traits::{Numeric, Truth},
+    tree::{NodeId, Tree},
+};
+
+/// A step of a [`Program`], which works on a stack of values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction<T> {
+    Push(T),
+    /// Pushes the value of the variable in the given slot.
+    Load(usize),
+    /// Replaces the top of the stack.
+    Unary(Operator),
+    /// Replaces the two topmost values, the top is the right operand.
+    Binary(Operator),
+    /// Replaces as many values as the function takes arguments.
+    Call(Function),
+    /// Replaces the top of the stack with 1 if it is true and 0 otherwise.
+    Truth,
+    Jump(usize),
+    /// Pops the top of the stack and jumps if it is false.
+    JumpUnless(usize),
+}
+
+/// A term compiled by [`compile`]. Variables are numbered in alphabetical order,
+/// the values passed to [`Program::evaluate`] follow [`Program::variables`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program<T> {
+    instructions: Vec<Instruction<T>>,
+    variables: Vec<String>,
+}
+
+enum Step<T> {
+    Visit(NodeId),
+    Emit(Instruction<T>),
+    /// Marks the position the jumps to this label go to.
+    Label(usize),
+}
+
+/// Copy of `term` with every `diff` replaced by the derivative it stands for.
+fn inline_derivatives<T: Numeric>(
+    term: &Tree<Token<T>>,
+) -> Result<Tree<Token<T>>, CalculationError> {
+    let mut out = Tree::new();
+    let Some(root) = term.root() else {
+        return Ok(out);
+    };
+    let mut ids: HashMap<NodeId, NodeId> = HashMap::new();
+    let mut result = Ok(());
+    term.traverse_postorder(root, &mut |(id, token)| {
+        if result.is_err() {
+            return;
+        }
+        let new = match (token, term.children(id)) {
+            (Token::Func(Function::DIFF), &[f, x]) => variable_name(term, x, Function::DIFF)
+                .and_then(|var| derivative_at(term, f, var))
+                .map(|derivative| match derivative.root() {
+                    Some(root) => out.graft(&derivative, root),
+                    None => out.push(Token::Value(T::default()), vec![]),
+                }),
+            (token, children) => {
+                let children = children.iter().map(|child| ids[child]).collect();
+                Ok(out.push(token.clone(), children))
+            }
+        };
+        match new {
+            Ok(new) => {
+                ids.insert(id, new);
+            }
+            Err(err) => result = Err(err),
+        }
+    });
+    result?;
+    out.set_root(ids[&root]);
+    Ok(out)
+}
+
+/// Compiles a term to instructions that evaluate it like [`calculate_with_variables`]
+/// without walking the tree. `&&`, `||` and `? :` jump over the operands they don't need,
+/// derivatives are computed once while compiling. Integrals can't be compiled.
+///
+/// [`calculate_with_variables`]: crate::calculate::calculate_with_variables
+pub fn compile<T: Numeric>(term: &Tree<Token<T>>) -> Result<Program<T>, CalculationError> {
+    let term = inline_derivatives(term)?;
+    let Some(root) = term.root() else {
+        return Ok(Program {
+            instructions: vec![Instruction::Push(T::default())],
+            variables: vec![],
+        });
+    };
+
+    let variables: Vec<String> = term
+        .iter()
+        .filter_map(|(_, token)| match token {
+            Token::Var(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut instructions = vec![];
+    let mut labels: Vec<usize> = vec![];
+    let new_label = |labels: &mut Vec<usize>| {
+        labels.push(0);
+        labels.len() - 1
+    };
+    let mut steps = vec![Step::Visit(root)];
+    while let Some(step) = steps.pop() {
+        let id = match step {
+            Step::Visit(id) => id,
+            Step::Emit(instruction) => {
+                instructions.push(instruction);
+                continue;
+            }
+            Step::Label(label) => {
+                labels[label] = instructions.len();
+                continue;
+            }
+        };
+        let token = term
+            .get(id)
+            .ok_or_else(|| CalculationError::MissingOperand(String::new()))?;
+        let children = term.children(id);
+        if children.len() < token.arity() {
+            return Err(CalculationError::MissingOperand(token.to_string()));
+        }
+
+        // Steps are popped in reverse order.
+        let mut next: Vec<Step<T>> = match token {
+            Token::Value(v) => vec![Step::Emit(Instruction::Push(*v))],
+            Token::Var(name) => {
+                let slot = variables.binary_search(name).unwrap_or_default();
+                vec![Step::Emit(Instruction::Load(slot))]
+            }
+            Token::Op(Operator::AND) => {
+                let (otherwise, end) = (new_label(&mut labels), new_label(&mut labels));
+                vec![
+                    Step::Visit(children[0]),
+                    Step::Emit(Instruction::JumpUnless(otherwise)),
+                    Step::Visit(children[1]),
+                    Step::Emit(Instruction::Truth),
+                    Step::Emit(Instruction::Jump(end)),
+                    Step::Label(otherwise),
+                    Step::Emit(Instruction::Push(T::from_bool(false))),
+                    Step::Label(end),
+                ]
+            }
+            Token::Op(Operator::OR) => {
+                let (otherwise, end) = (new_label(&mut labels), new_label(&mut labels));
+                vec![
+                    Step::Visit(children[0]),
+                    Step::Emit(Instruction::JumpUnless(otherwise)),
+                    Step::Emit(Instruction::Push(T::from_bool(true))),
+                    Step::Emit(Instruction::Jump(end)),
+                    Step::Label(otherwise),
+                    Step::Visit(children[1]),
+                    Step::Emit(Instruction::Truth),
+                    Step::Label(end),
+                ]
+            }
+            Token::Op(Operator::COND) => {
+                let (otherwise, end) = (new_label(&mut labels), new_label(&mut labels));
+                vec![
+                    Step::Visit(children[0]),
+                    Step::Emit(Instruction::JumpUnless(otherwise)),
+                    Step::Visit(children[1]),
+                    Step::Emit(Instruction::Jump(end)),
+                    Step::Label(otherwise),
+                    Step::Visit(children[2]),
+                    Step::Label(end),
+                ]
+            }
+            Token::Func(func @ (Function::FACTOR | Function::SOLVE)) => {
+                return Err(CalculationError::NotAValue(*func));
+            }
+            Token::Func(func @ Function::INTEGRATE) => {
+                return Err(CalculationError::NotCompilable(*func));
+            }
+            Token::Op(op) if op.arity() == 0 => {
+                return Err(CalculationError::InvalidOperator(*op));
+            }
+            Token::Op(_) | Token::Func(_) => {
+                let instruction = match *token {
+                    Token::Func(func) => Instruction::Call(func),
+                    Token::Op(op) if op.arity() == 1 => Instruction::Unary(op),
+                    Token::Op(op) => Instruction::Binary(op),
+                    _ => unreachable!(),
+                };
+                children
+                    .iter()
+                    .map(|&child| Step::Visit(child))
+                    .chain([Step::Emit(instruction)])
+                    .collect()
+            }
+            Token::Empty => return Err(CalculationError::MissingOperand(String::new())),
+        };
+        next.reverse();
+        steps.append(&mut next);
+    }
+
+    for instruction in instructions.iter_mut() {
+        if let Instruction::Jump(target) | Instruction::JumpUnless(target) = instruction {
+            *target = labels[*target];
+        }
+    }
+    Ok(Program {
+        instructions,
+        variables,
+    })
+}
+
+impl<T: Numeric> Program<T> {
+    pub fn instructions(&self) -> &[Instruction<T>] {
+        &self.instructions
+    }
+
+    /// Names of the variables in the order their values are expected.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Evaluates the program with the values of [`Program::variables`].
+    pub fn evaluate(&self, values: &[T]) -> Result<T, CalculationError> {
+        self.evaluate_with_stack(values, &mut Vec::new())
+    }
+
+    /// Like [`Program::evaluate`] but reuses `stack`, so repeated evaluation doesn't allocate.
+    pub fn evaluate_with_stack(
+        &self,
+        values: &[T],
+        stack: &mut Vec<T>,
+    ) -> Result<T, CalculationError> {
+        if let Some(missing) = self.variables.get(values.len()) {
+            return Err(CalculationError::UnknownVariable(missing.clone()));
+        }
+        stack.clear();
+        let mut pc = 0;
+        while let Some(instruction) = self.instructions.get(pc) {
+            pc += 1;
+            match *instruction {
+                Instruction::Push(v) => stack.push(v),
+                Instruction::Load(slot) => stack.push(values[slot]),
+                Instruction::Unary(op) => {
+                    let a = stack.last_mut().expect("compiled programs are balanced");
+                    *a = apply_unary_operator(op, *a)?;
+                }
+                Instruction::Binary(op) => {
+                    let b = stack.pop().expect("compiled programs are balanced");
+                    let a = stack.last_mut().expect("compiled programs are balanced");
+                    *a = apply_operator(op, *a, b)?;
+                }
+                Instruction::Call(func) => {
+                    let args = stack.len() - func.arity();
+                    let result = apply_function(func, &stack[args..])?;
+                    stack.truncate(args);
+                    stack.push(result);
+                }
+                Instruction::Truth => {
+                    let a = stack.last_mut().expect("compiled programs are balanced");
+                    *a = T::from_bool(a.is_true());
+                }
+                Instruction::Jump(target) => pc = target,
+                Instruction::JumpUnless(target) => {
+                    if !stack
+                        .pop()
+                        .expect("compiled programs are balanced")
+                        .is_true()
+                    {
+                        pc = target;
+                    }
+                }
+            }
+        }
+        stack
+            .pop()
+            .ok_or_else(|| CalculationError::MissingOperand(String::new()))
+    }
+
+    /// Evaluates the program with the values of its variables looked up by name.
+    pub fn evaluate_with_variables(&self, variables: &Variables<T>) -> Result<T, CalculationError> {
+        let values = self
+            .variables
+            .iter()
+            .map(|name| {
+                variables
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| CalculationError::UnknownVariable(name.clone()))
+            })
+            .collect::<Result<Vec<T>, CalculationError>>()?;
+        self.evaluate(&values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{calculate::calculate_with_variables, parse::parse_term, traits::Constants};
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    #[test]
+    fn test_instructions() {
+        let program = compile(&parse_term("-x * 2.5 + y", &CONSTANTS).unwrap()).unwrap();
+        assert_eq!(["x", "y"], program.variables());
+        assert_eq!(
+            &[
+                Instruction::Load(0),
+                Instruction::Unary(Operator::NEG),
+                Instruction::Push(2.5),
+                Instruction::Binary(Operator::MUL),
+                Instruction::Load(1),
+                Instruction::Binary(Operator::ADD),
+            ],
+            program.instructions()
+        );
+        assert_eq!(Ok(-4.0), program.evaluate(&[2.0, 1.0]));
+        assert_eq!(
+            Err(CalculationError::UnknownVariable("y".to_owned())),
+            program.evaluate(&[2.0])
+        );
+    }
+
+    #[test]
+    fn test_same_as_tree_walk() {
+        let terms = [
+            "x^3 - 2x^2 + sin(x) * exp(-x / 10)",
+            "x > 1 && y != 0 ? gcd(6, 4) : -1",
+            "x < 0 || 1 / (x - 1) > 0",
+            "y == 0 && 1 / y",
+            "diff(x^2 * y, x) + nCr(5, 2) + 3!",
+            "x // 0.3 + x mod 0.3 + x rem 0.3",
+        ];
+        for term in terms {
+            let tree = parse_term(term, &CONSTANTS).unwrap();
+            let program = compile(&tree).unwrap();
+            let mut stack = vec![];
+            for (x, y) in [(-2.0, 0.0), (0.5, 1.0), (1.0, 3.0), (2.5, -1.5)] {
+                let variables = Variables::from([("x".to_owned(), x), ("y".to_owned(), y)]);
+                let values: Vec<f64> = program
+                    .variables()
+                    .iter()
+                    .map(|name| variables[name])
+                    .collect();
+                assert_eq!(
+                    calculate_with_variables(&tree, &variables),
+                    program.evaluate_with_stack(&values, &mut stack),
+                    "{term} at {x}, {y}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_not_compilable() {
+        let compiled = |s: &str| compile(&parse_term(s, &CONSTANTS).unwrap());
+        assert_eq!(
+            Err(CalculationError::NotCompilable(Function::INTEGRATE)),
+            compiled("integrate(x, x, 0, 1)")
+        );
+        assert_eq!(
+            Err(CalculationError::NotAValue(Function::FACTOR)),
+            compiled("factor(12)")
+        );
+        assert_eq!(Ok(0.0), compile(&Tree::new()).and_then(|p| p.evaluate(&[])));
+    }
+}
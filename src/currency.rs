@@ -0,0 +1,351 @@
+//! Currency codes like `EUR` as units, converted with exchange rates from a local file.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use thiserror::Error;
+
+use crate::{
+    calculate::{CalculationError, Variables, calculate_subtree, calculate_with_variables},
+    datetime::days_since_epoch,
+    interval::Endpoint,
+    token::{Operator, Token},
+    traits::Numeric,
+    tree::Tree,
+    units::{Dimension, Quantity},
+};
+
+#[derive(Error, Debug)]
+pub enum RateError {
+    #[error("Exchange rates can't be read: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Line {0} of the exchange rates is not `currency,rate,date`: {1}")]
+    InvalidLine(usize, String),
+    #[error("{0} is not a currency code of three letters")]
+    InvalidCurrency(String),
+    #[error("Exchange rate for {0} has to be a positive number")]
+    InvalidRate(String),
+    #[error("Date {0} is not of the form YYYY-MM-DD")]
+    InvalidDate(String),
+    #[error("Exchange rates in {0} files are not supported")]
+    UnsupportedFormat(String),
+    #[cfg(feature = "serde")]
+    #[error("Exchange rates are not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Units of a currency that one unit of the reference currency buys on `date`.
+/// The reference currency itself has the rate `1`, which one it is doesn't matter
+/// as long as all rates of a file share it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rate {
+    pub currency: String,
+    pub rate: f64,
+    /// Day the rate was published, `YYYY-MM-DD`.
+    pub date: String,
+}
+
+/// Exchange rates by currency code. Rates that are more than `max_age` days old
+/// are stale and can't be used.
+#[derive(Clone, Debug, Default)]
+pub struct Rates {
+    rates: HashMap<String, (Rate, i64)>,
+    max_age: i64,
+}
+
+impl Rates {
+    /// Rates older than a week are stale, unless [`Rates::with_max_age`] says otherwise.
+    pub const MAX_AGE: u32 = 7;
+
+    pub fn new(rates: impl IntoIterator<Item = Rate>) -> Result<Self, RateError> {
+        let rates = rates
+            .into_iter()
+            .map(|rate| {
+                let code = rate.currency.to_uppercase();
+                if !is_currency_code(&code) {
+                    return Err(RateError::InvalidCurrency(rate.currency));
+                }
+                if !(rate.rate > 0.0 && rate.rate.is_finite()) {
+                    return Err(RateError::InvalidRate(rate.currency));
+                }
+                let day = days_since_epoch(&rate.date)
+                    .ok_or(RateError::InvalidDate(rate.date.clone()))?;
+                Ok((code, (rate, day)))
+            })
+            .collect::<Result<_, RateError>>()?;
+        Ok(Rates {
+            rates,
+            max_age: Self::MAX_AGE.into(),
+        })
+    }
+
+    pub fn with_max_age(mut self, days: u32) -> Self {
+        self.max_age = days.into();
+        self
+    }
+
+    /// Reads lines of `currency,rate,date` like `USD,1.0842,2026-10-16`.
+    /// Empty lines, comments starting with `#` and a header starting with `currency` are skipped.
+    pub fn from_csv(text: &str) -> Result<Self, RateError> {
+        let rates = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !(line.is_empty() || line.starts_with('#')))
+            .filter(|(_, line)| !line.starts_with("currency"))
+            .map(|(number, line)| {
+                let invalid = || RateError::InvalidLine(number, line.to_owned());
+                let [currency, rate, date] = line
+                    .split(',')
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .map_err(|_| invalid())?;
+                Ok(Rate {
+                    currency: currency.to_owned(),
+                    rate: rate.parse().map_err(|_| invalid())?,
+                    date: date.to_owned(),
+                })
+            })
+            .collect::<Result<Vec<_>, RateError>>()?;
+        Self::new(rates)
+    }
+
+    /// Reads an array of rates like `[{"currency": "USD", "rate": 1.0842, "date": "2026-10-16"}]`.
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Self, RateError> {
+        Self::new(serde_json::from_str::<Vec<Rate>>(text)?)
+    }
+
+    /// Reads a `.csv` file, or a `.json` file with the `serde` feature.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RateError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        match extension.to_lowercase().as_str() {
+            "csv" => Self::from_csv(&text),
+            #[cfg(feature = "serde")]
+            "json" => Self::from_json(&text),
+            _ => Err(RateError::UnsupportedFormat(extension.to_owned())),
+        }
+    }
+
+    /// Rate of `currency` if it exists and isn't stale on the day `today`.
+    pub fn rate(&self, currency: &str, today: i64) -> Result<f64, CalculationError> {
+        let code = currency.to_uppercase();
+        match self.rates.get(&code) {
+            None => Err(CalculationError::MissingRate(code)),
+            Some((rate, day)) if today - day > self.max_age => {
+                Err(CalculationError::StaleRate(code, rate.date.clone()))
+            }
+            Some((rate, _)) => Ok(rate.rate),
+        }
+    }
+
+    /// Whether there is a rate for `currency`, stale or not.
+    pub fn contains(&self, currency: &str) -> bool {
+        self.rates.contains_key(&currency.to_uppercase())
+    }
+
+    /// Variables of `term` that have a rate, in the order they are written. Variable
+    /// names are lowercase once parsed, so `eur` is a currency as much as `EUR`.
+    pub fn currency_codes<T: Numeric>(&self, term: &Tree<Token<T>>) -> Vec<String> {
+        let mut codes: Vec<String> = vec![];
+        if let Some(root) = term.root() {
+            term.traverse_inorder(root, &mut |(_, token)| match token {
+                Token::Var(name) if self.contains(name) && !codes.contains(name) => {
+                    codes.push(name.clone())
+                }
+                _ => (),
+            });
+        }
+        codes
+    }
+
+    /// Values of the currency codes of `term` in the reference currency.
+    pub fn variables<T: Endpoint>(
+        &self,
+        term: &Tree<Token<Quantity<T>>>,
+        today: i64,
+    ) -> Result<Variables<Quantity<T>>, CalculationError> {
+        self.currency_codes(term)
+            .into_iter()
+            .map(|code| {
+                let value = T::from_f64(1.0 / self.rate(&code, today)?)
+                    .ok_or_else(|| CalculationError::Undefined(code.clone()))?;
+                Ok((code, Quantity::new(value, Dimension::CURRENCY)))
+            })
+            .collect()
+    }
+}
+
+/// Whether `word` looks like an ISO 4217 code, three capital letters like `EUR`.
+pub fn is_currency_code(word: &str) -> bool {
+    word.len() == 3 && word.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Result of a term with currency codes together with its unit. Conversions like
+/// `100 EUR to USD` are in the currency they convert to, other amounts of money
+/// in the first currency of the term, so `100 EUR + 20 USD` is in euros.
+/// Returns `None` if the term has no currency codes with a rate.
+pub fn currency_result<T: Endpoint>(
+    term: &Tree<Token<Quantity<T>>>,
+    rates: &Rates,
+    today: i64,
+) -> Result<Option<(T, String)>, CalculationError> {
+    let variables = rates.variables(term, today)?;
+    let (Some(root), Some(first)) = (term.root(), rates.currency_codes(term).into_iter().next())
+    else {
+        return Ok(None);
+    };
+    let result = calculate_with_variables(term, &variables)?;
+    if let (Some(Token::Op(Operator::TO)), Some(unit)) = (term.get(root), term.child(root, 1)) {
+        // The unit is shown as written, only with the codes in capitals.
+        let mut written = Tree::new();
+        for (id, token) in term.iter() {
+            let token = match token {
+                Token::Var(name) if variables.contains_key(name) => Token::Var(name.to_uppercase()),
+                token => token.clone(),
+            };
+            written.push(token, term.children(id).to_vec());
+        }
+        written.set_root(unit);
+        // Checks that the unit evaluates, the result is already in multiples of it.
+        calculate_subtree(term, unit, &variables)?;
+        return Ok(Some((result.value(), written.to_string())));
+    }
+
+    let dimension = result.dimension();
+    let rate = T::from_f64(
+        rates
+            .rate(&first, today)?
+            .powi(dimension.currency_exponent().into()),
+    )
+    .ok_or_else(|| CalculationError::Undefined(first.clone()))?;
+    let unit = match dimension.currency_exponent() {
+        0 if dimension.is_none() => String::new(),
+        _ => dimension.to_string().replace('¤', &first.to_uppercase()),
+    };
+    Ok(Some((result.value() * rate, unit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_term;
+
+    const RATES: &str = "\
+# Rates against the euro
+currency,rate,date
+EUR,1,2026-10-16
+USD,1.25,2026-10-16
+CHF,0.9,2026-09-01
+";
+
+    fn result(term: &str, today: i64) -> Result<Option<(f64, String)>, CalculationError> {
+        let rates = Rates::from_csv(RATES).unwrap();
+        let term = parse_term(term, &Quantity::constants()).unwrap();
+        currency_result(&term, &rates, today)
+    }
+
+    #[test]
+    fn test_rates() {
+        let rates = Rates::from_csv(RATES).unwrap();
+        let today = days_since_epoch("2026-10-18").unwrap();
+        assert_eq!(Ok(1.25), rates.rate("usd", today));
+        assert_eq!(
+            Err(CalculationError::MissingRate("GBP".to_owned())),
+            rates.rate("GBP", today)
+        );
+        assert_eq!(
+            Err(CalculationError::StaleRate(
+                "CHF".to_owned(),
+                "2026-09-01".to_owned()
+            )),
+            rates.rate("CHF", today)
+        );
+        assert_eq!(Ok(0.9), rates.clone().with_max_age(60).rate("CHF", today));
+
+        assert!(matches!(
+            Rates::from_csv("USD,1.25"),
+            Err(RateError::InvalidLine(1, _))
+        ));
+        assert!(rates.contains("eur") && !rates.contains("GBP"));
+        assert!(is_currency_code("EUR"));
+        assert!(!is_currency_code("eur") && !is_currency_code("EURO") && !is_currency_code("E1R"));
+
+        assert!(matches!(
+            Rates::from_csv("EURO,1,2026-10-16"),
+            Err(RateError::InvalidCurrency(_))
+        ));
+        assert!(matches!(
+            Rates::from_csv("USD,-1,2026-10-16"),
+            Err(RateError::InvalidRate(_))
+        ));
+        assert!(matches!(
+            Rates::from_csv("USD,1.25,16.10.2026"),
+            Err(RateError::InvalidDate(_))
+        ));
+    }
+
+    #[test]
+    fn test_currency_result() {
+        let today = days_since_epoch("2026-10-18").unwrap();
+        assert_eq!(
+            Ok(Some((125.0, "USD".to_owned()))),
+            result("100 EUR to USD", today)
+        );
+        assert_eq!(
+            Ok(Some((116.0, "EUR".to_owned()))),
+            result("100 EUR + 20 USD", today)
+        );
+        assert_eq!(
+            Ok(Some((0.8, String::new()))),
+            result("1 USD / (1 EUR)", today)
+        );
+        assert_eq!(
+            Ok(Some((10.0, "EUR / h".to_owned()))),
+            result("80 EUR / (8 h) to EUR / h", today)
+        );
+        assert_eq!(Ok(None), result("3 m + 2 m", today));
+
+        assert_eq!(
+            Ok(Some((8.0, "EUR".to_owned()))),
+            result("10 usd to eur", today)
+        );
+
+        // Codes without a rate are variables like any other.
+        assert_eq!(
+            Err(CalculationError::UnknownVariable("gbp".to_owned())),
+            result("10 GBP to EUR", today)
+        );
+        assert_eq!(
+            Err(CalculationError::UnknownVariable("foo".to_owned())),
+            result("10 EUR * foo", today)
+        );
+        assert_eq!(Ok(None), result("3 abc", today));
+        assert_eq!(
+            Err(CalculationError::StaleRate(
+                "CHF".to_owned(),
+                "2026-09-01".to_owned()
+            )),
+            result("10 CHF to EUR", today)
+        );
+        assert!(matches!(
+            result("10 EUR + 2 m", today),
+            Err(CalculationError::IncompatibleUnits(_, _))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        let rates =
+            Rates::from_json(r#"[{"currency": "USD", "rate": 1.25, "date": "2026-10-16"}]"#)
+                .unwrap();
+        assert_eq!(
+            Ok(1.25),
+            rates.rate("USD", days_since_epoch("2026-10-18").unwrap())
+        );
+    }
+}
@@ -0,0 +1,707 @@
+//! Dates, times of day and durations with ISO 8601 literals like `2026-10-18`, `17:45`
+//! and `PT8H15M`.
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+    sync::LazyLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use regex::Regex;
+
+use crate::{
+    token::Operator,
+    traits::{
+        Checked, Combinatorics, Commensurable, Constants, Division, Elementary, FromBinaryStr,
+        FromHexStr, FromScientificStr, FromStrError, FromTemporalStr, One, Real,
+    },
+};
+
+const MINUTE: f64 = 60.0;
+const HOUR: f64 = 3600.0;
+const DAY: f64 = 86_400.0;
+
+/// Durations by name, months and years are left out as their length varies.
+const DURATIONS: [(&str, f64); 14] = [
+    ("s", 1.0),
+    ("second", 1.0),
+    ("seconds", 1.0),
+    ("min", MINUTE),
+    ("minute", MINUTE),
+    ("minutes", MINUTE),
+    ("h", HOUR),
+    ("hour", HOUR),
+    ("hours", HOUR),
+    ("day", DAY),
+    ("days", DAY),
+    ("week", 7.0 * DAY),
+    ("weeks", 7.0 * DAY),
+    ("wk", 7.0 * DAY),
+];
+
+/// Dates with an optional time of day, times of day and ISO 8601 durations.
+const LITERAL: &str = r"\d{4}-\d{2}-\d{2}(?:[Tt]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?[Zz]?)?|\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?|P(?:\d|T\d)[\d.WDTHMS]*[WDHMS]";
+
+static LITERAL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(LITERAL).unwrap());
+static LEADING_LITERAL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!(r"\A(?:{LITERAL})")).unwrap());
+
+/// Whether `input` has a date like `2026-10-18`, a time like `17:45` or a duration like `P90D`.
+pub fn has_literal(input: &str) -> bool {
+    LITERAL_REGEX.is_match(input)
+}
+
+/// The date, time or duration that `input` starts with.
+pub(crate) fn leading_literal(input: &str) -> Option<&str> {
+    LEADING_LITERAL_REGEX.find(input).map(|m| m.as_str())
+}
+
+/// Whether `word` names a duration like `days` or `h`.
+pub fn is_duration_word(word: &str) -> bool {
+    DURATIONS
+        .iter()
+        .any(|&(name, _)| name == word.to_lowercase())
+}
+
+/// What a [`Temporal`] value stands for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Kind {
+    /// Plain number like the `90` of `90 days`.
+    #[default]
+    Number,
+    /// Length of time like `PT8H15M`.
+    Duration,
+    /// Day like `2026-10-18`, or a point in time on it like `2026-10-18T17:45`.
+    Date,
+    /// Time of day like `17:45`, which wraps around at midnight.
+    Time,
+}
+
+/// Kind of the result of `a op b`, `None` if it has no meaning. Differences of dates or
+/// times are durations, durations can be added to them but dates can't be added to each other.
+fn combined(op: Operator, a: Kind, b: Kind) -> Option<Kind> {
+    use Kind::*;
+    match (op, a, b) {
+        (_, Number, Number) => Some(Number),
+        (Operator::ADD | Operator::SUB, Duration, Duration) => Some(Duration),
+        (Operator::ADD, Duration, point @ (Date | Time)) => Some(point),
+        (Operator::ADD | Operator::SUB, point @ (Date | Time), Duration) => Some(point),
+        (Operator::SUB, Date, Date) | (Operator::SUB, Time, Time) => Some(Duration),
+        (Operator::MUL, Duration, Number) | (Operator::MUL, Number, Duration) => Some(Duration),
+        (Operator::DIV | Operator::IDIV, Duration, Number) => Some(Duration),
+        (Operator::DIV | Operator::IDIV | Operator::TO, Duration, Duration) => Some(Number),
+        (Operator::MOD | Operator::REM, Duration, Duration) => Some(Duration),
+        (Operator::LT | Operator::LE | Operator::GT | Operator::GE, a, b) if a == b => Some(Number),
+        (Operator::IS | Operator::NE, a, b) if a == b => Some(Number),
+        _ => None,
+    }
+}
+
+/// A number, a duration or a point in time, kept in seconds. Dates count from
+/// 1970-01-01T00:00 in UTC and times from midnight.
+///
+/// `2026-10-18 + 90 days` is the date `2027-01-16` and `17:45 - 09:30` the duration `PT8H15M`,
+/// results are shown in ISO 8601. Terms without a meaning like the sum of two dates fail.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Temporal {
+    value: f64,
+    kind: Kind,
+    /// Name a duration was written with, to show it the same way.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    name: Option<&'static str>,
+}
+
+impl Temporal {
+    pub fn new(value: f64, kind: Kind) -> Self {
+        // Times of day wrap around at midnight.
+        let value = match kind {
+            Kind::Time => value.rem_euclid(DAY),
+            _ => value,
+        };
+        Temporal {
+            value,
+            kind,
+            name: None,
+        }
+    }
+
+    pub fn number(value: f64) -> Self {
+        Self::new(value, Kind::Number)
+    }
+
+    pub fn duration(seconds: f64) -> Self {
+        Self::new(seconds, Kind::Duration)
+    }
+
+    /// Start of the day `days` after 1970-01-01.
+    pub fn date(days: i64) -> Self {
+        Self::new(days as f64 * DAY, Kind::Date)
+    }
+
+    /// Number, or seconds of a duration, a date since 1970-01-01 or a time since midnight.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// `e` and `pi` as numbers.
+    pub fn constants() -> Constants<Self> {
+        Constants {
+            e: Self::number(std::f64::consts::E),
+            pi: Self::number(std::f64::consts::PI),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.kind == Kind::Number && self.value == 0.0
+    }
+
+    fn number_value(self) -> Option<f64> {
+        (self.kind == Kind::Number).then_some(self.value)
+    }
+
+    /// Result of `self op rhs` with the value `value`, a number that isn't one if undefined.
+    fn combine(self, op: Operator, rhs: Self, value: impl FnOnce(f64, f64) -> f64) -> Self {
+        match combined(op, self.kind, rhs.kind) {
+            Some(kind) => Self::new(value(self.value, rhs.value), kind),
+            None => Self::number(f64::NAN),
+        }
+    }
+}
+
+impl PartialEq for Temporal {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.kind == other.kind
+    }
+}
+
+/// Values of different kinds are unordered, except for a plain zero.
+impl PartialOrd for Temporal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.kind == other.kind || self.is_zero() || other.is_zero() {
+            true => self.value.partial_cmp(&other.value),
+            false => None,
+        }
+    }
+}
+
+impl Commensurable for Temporal {
+    fn commensurable(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+
+    fn defined(&self, op: Operator, other: &Self) -> bool {
+        combined(op, self.kind, other.kind).is_some()
+    }
+}
+
+/// Sums and differences that have no meaning are NaN, the calculation checks for them first.
+impl Add for Temporal {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.combine(Operator::ADD, rhs, |a, b| a + b)
+    }
+}
+
+impl Sub for Temporal {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.combine(Operator::SUB, rhs, |a, b| a - b)
+    }
+}
+
+/// Negating a date or time is NaN.
+impl Neg for Temporal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self.kind {
+            Kind::Number | Kind::Duration => Self::new(-self.value, self.kind),
+            Kind::Date | Kind::Time => Self::number(f64::NAN),
+        }
+    }
+}
+
+impl Mul for Temporal {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.combine(Operator::MUL, rhs, |a, b| a * b)
+    }
+}
+
+impl Div for Temporal {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self.combine(Operator::DIV, rhs, |a, b| a / b)
+    }
+}
+
+impl One for Temporal {
+    fn one() -> Self {
+        Self::number(1.0)
+    }
+}
+
+impl Division for Temporal {
+    fn quotient(self, rhs: Self) -> Option<Self> {
+        (rhs.value != 0.0).then(|| self / rhs)
+    }
+    fn floor_div(self, rhs: Self) -> Option<Self> {
+        let value = self.value.floor_div(rhs.value)?;
+        Some(self.combine(Operator::IDIV, rhs, |_, _| value))
+    }
+    fn modulo(self, rhs: Self) -> Option<Self> {
+        let value = self.value.modulo(rhs.value)?;
+        Some(self.combine(Operator::MOD, rhs, |_, _| value))
+    }
+    fn remainder(self, rhs: Self) -> Option<Self> {
+        let value = self.value.remainder(rhs.value)?;
+        Some(self.combine(Operator::REM, rhs, |_, _| value))
+    }
+}
+
+impl Checked for Temporal {}
+
+/// Only numbers have factorials, divisors or binomials.
+impl Combinatorics for Temporal {
+    fn factorial(self) -> Option<Self> {
+        self.number_value()?.factorial().map(Self::number)
+    }
+    fn permutations(self, k: Self) -> Option<Self> {
+        let n = self.number_value()?;
+        n.permutations(k.number_value()?).map(Self::number)
+    }
+    fn combinations(self, k: Self) -> Option<Self> {
+        let n = self.number_value()?;
+        n.combinations(k.number_value()?).map(Self::number)
+    }
+    fn gcd(self, other: Self) -> Option<Self> {
+        let a = self.number_value()?;
+        a.gcd(other.number_value()?).map(Self::number)
+    }
+    fn lcm(self, other: Self) -> Option<Self> {
+        let a = self.number_value()?;
+        a.lcm(other.number_value()?).map(Self::number)
+    }
+    fn is_prime(&self) -> Option<bool> {
+        self.number_value()?.is_prime()
+    }
+    fn prime_factors(self) -> Option<Vec<Self>> {
+        let factors = self.number_value()?.prime_factors()?;
+        Some(factors.into_iter().map(Self::number).collect())
+    }
+}
+
+/// Only numbers are taken to powers or passed to functions.
+impl Elementary for Temporal {
+    fn power(self, exponent: Self) -> Option<Self> {
+        let base = self.number_value()?;
+        base.power(exponent.number_value()?).map(Self::number)
+    }
+    fn root(self) -> Option<Self> {
+        self.number_value()?.root().map(Self::number)
+    }
+    fn sine(self) -> Option<Self> {
+        self.number_value()?.sine().map(Self::number)
+    }
+    fn cosine(self) -> Option<Self> {
+        self.number_value()?.cosine().map(Self::number)
+    }
+    fn tangent(self) -> Option<Self> {
+        self.number_value()?.tangent().map(Self::number)
+    }
+    fn exponential(self) -> Option<Self> {
+        self.number_value()?.exponential().map(Self::number)
+    }
+    fn logarithm(self) -> Option<Self> {
+        self.number_value()?.logarithm().map(Self::number)
+    }
+}
+
+/// The numerical methods work with the value in seconds.
+impl Real for Temporal {
+    fn to_f64(self) -> f64 {
+        self.value
+    }
+    fn from_f64(x: f64) -> Option<Self> {
+        Some(Self::number(x))
+    }
+}
+
+impl FromBinaryStr for Temporal {
+    fn from_binary_str(s: &str) -> Result<Self, FromStrError> {
+        f64::from_binary_str(s).map(Self::number)
+    }
+}
+
+impl FromHexStr for Temporal {
+    fn from_hex_str(s: &str) -> Result<Self, FromStrError> {
+        f64::from_hex_str(s).map(Self::number)
+    }
+}
+
+impl FromScientificStr for Temporal {
+    fn from_scientific_str(s: &str) -> Result<Self, FromStrError> {
+        f64::from_scientific_str(s).map(Self::number)
+    }
+}
+
+impl FromTemporalStr for Temporal {
+    fn from_temporal_str(s: &str) -> Option<Result<Self, FromStrError>> {
+        Some(s.parse())
+    }
+}
+
+/// Days since 1970-01-01 of a date like `2026-10-18`.
+pub(crate) fn days_since_epoch(date: &str) -> Option<i64> {
+    let mut parts = date.trim().splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let length = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=length).contains(&day) {
+        return None;
+    }
+    // Counts from March, so the leap day is the last day of the year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+/// Year, month and day of the day `days` after 1970-01-01, the inverse of [`days_since_epoch`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Days since 1970-01-01 of the current day in UTC.
+pub fn today() -> i64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    (seconds / 86_400) as i64
+}
+
+/// Seconds since midnight of a time like `17:45` or `17:45:30.5`.
+fn parse_time(time: &str) -> Option<f64> {
+    let mut parts = time.splitn(3, ':');
+    let (hours, minutes) = (parts.next()?, parts.next()?);
+    let two_digits = |part: &str| part.len() == 2 && part.chars().all(|c| c.is_ascii_digit());
+    if !(two_digits(hours) && two_digits(minutes)) {
+        return None;
+    }
+    let (hours, minutes) = (hours.parse::<f64>().ok()?, minutes.parse::<f64>().ok()?);
+    let seconds = match parts.next() {
+        Some(seconds) if seconds.starts_with(|c: char| c.is_ascii_digit()) => {
+            seconds.parse::<f64>().ok()?
+        }
+        Some(_) => return None,
+        None => 0.0,
+    };
+    (hours < 24.0 && minutes < 60.0 && seconds < 60.0)
+        .then_some(hours * HOUR + minutes * MINUTE + seconds)
+}
+
+/// Seconds of an ISO 8601 duration like `P1DT2H30M` or `P2W`, without months and years.
+fn parse_duration(duration: &str) -> Option<f64> {
+    let designators = [
+        ('W', 7.0 * DAY),
+        ('D', DAY),
+        ('T', 0.0),
+        ('H', HOUR),
+        ('M', MINUTE),
+        ('S', 1.0),
+    ];
+    let mut rest = duration.strip_prefix('P')?;
+    let (mut seconds, mut next, mut parts) = (0.0, 0, 0);
+    while !rest.is_empty() {
+        let length = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (number, tail) = rest.split_at(length);
+        let designator = tail.chars().next()?;
+        // Designators come in order, and minutes only after the `T`.
+        let time = designators[..next].iter().any(|&(d, _)| d == 'T');
+        let position = designators[next..]
+            .iter()
+            .position(|&(d, _)| d == designator)?
+            + next;
+        match (designator, number.is_empty()) {
+            ('T', true) => {}
+            ('H' | 'M' | 'S', false) if time => parts += 1,
+            ('W' | 'D', false) if !time => parts += 1,
+            _ => return None,
+        }
+        if designator != 'T' {
+            seconds += number.parse::<f64>().ok()? * designators[position].1;
+        }
+        next = position + 1;
+        rest = &tail[1..];
+    }
+    (parts > 0 && !duration.ends_with('T')).then_some(seconds)
+}
+
+/// Reads dates like `2026-10-18` or `2026-10-18T17:45`, times like `17:45`,
+/// ISO 8601 durations like `PT8H15M`, names of durations like `days` and numbers.
+impl FromStr for Temporal {
+    type Err = FromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow!("{} is not a date, time or duration", s);
+        let upper = s.to_uppercase();
+        let upper = upper.strip_suffix('Z').unwrap_or(&upper);
+        if let Some(&(name, seconds)) = DURATIONS
+            .iter()
+            .find(|&&(name, _)| name == s.to_lowercase())
+        {
+            return Ok(Temporal {
+                name: Some(name),
+                ..Self::duration(seconds)
+            });
+        }
+        if upper.starts_with('P') {
+            return parse_duration(upper)
+                .map(Self::duration)
+                .ok_or_else(|| invalid().into());
+        }
+        if let Some((date, time)) = upper.split_once('T') {
+            let days = days_since_epoch(date).ok_or_else(invalid)?;
+            let seconds = parse_time(time).ok_or_else(invalid)?;
+            return Ok(Self::new(days as f64 * DAY + seconds, Kind::Date));
+        }
+        if upper.contains(':') {
+            return parse_time(upper)
+                .map(|seconds| Self::new(seconds, Kind::Time))
+                .ok_or_else(|| invalid().into());
+        }
+        if upper.get(4..5) == Some("-") {
+            return days_since_epoch(upper)
+                .map(Self::date)
+                .ok_or_else(|| invalid().into());
+        }
+        match s.starts_with(|c: char| c.is_ascii_digit()) {
+            true => Ok(Self::number(s.parse::<f64>()?)),
+            false => Err(invalid().into()),
+        }
+    }
+}
+
+/// Seconds as `hh:mm`, with `:ss` if there are any.
+fn write_clock(f: &mut fmt::Formatter<'_>, seconds: f64) -> fmt::Result {
+    let hours = (seconds / HOUR).floor();
+    let minutes = ((seconds - hours * HOUR) / MINUTE).floor();
+    let seconds = seconds - hours * HOUR - minutes * MINUTE;
+    write!(f, "{:02}:{:02}", hours, minutes)?;
+    match seconds {
+        0.0 => Ok(()),
+        s if s < 10.0 => write!(f, ":0{}", s),
+        s => write!(f, ":{}", s),
+    }
+}
+
+/// ISO 8601, `2027-01-16`, `2026-10-18T17:45`, `17:45` or `PT8H15M`.
+/// Durations like `days` are shown as they were written.
+impl fmt::Display for Temporal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.name {
+            return write!(f, "{}", name);
+        }
+        if !self.value.is_finite() {
+            return write!(f, "{}", self.value);
+        }
+        match self.kind {
+            Kind::Number => write!(f, "{}", self.value),
+            Kind::Duration => {
+                if self.value < 0.0 {
+                    write!(f, "-")?;
+                }
+                let seconds = self.value.abs();
+                let days = (seconds / DAY).floor();
+                let hours = ((seconds - days * DAY) / HOUR).floor();
+                let minutes = ((seconds - days * DAY - hours * HOUR) / MINUTE).floor();
+                let seconds = seconds - days * DAY - hours * HOUR - minutes * MINUTE;
+                write!(f, "P")?;
+                if days > 0.0 {
+                    write!(f, "{}D", days)?;
+                }
+                if hours + minutes + seconds > 0.0 || days == 0.0 {
+                    write!(f, "T")?;
+                }
+                if hours > 0.0 {
+                    write!(f, "{}H", hours)?;
+                }
+                if minutes > 0.0 {
+                    write!(f, "{}M", minutes)?;
+                }
+                match seconds > 0.0 || self.value == 0.0 {
+                    true => write!(f, "{}S", seconds),
+                    false => Ok(()),
+                }
+            }
+            Kind::Date => {
+                let days = (self.value / DAY).floor();
+                let (year, month, day) = civil_from_days(days as i64);
+                write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+                match self.value - days * DAY {
+                    0.0 => Ok(()),
+                    seconds => {
+                        write!(f, "T")?;
+                        write_clock(f, seconds)
+                    }
+                }
+            }
+            Kind::Time => write_clock(f, self.value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate::{CalculationError, calculate_result},
+        parse::{ParsingError, parse_term},
+    };
+
+    fn calculate(term: &str) -> Result<String, CalculationError> {
+        let term = parse_term(term, &Temporal::constants()).unwrap();
+        calculate_result(&term).map(|result| result.to_string())
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(Some(0), days_since_epoch("1970-01-01"));
+        assert_eq!(Some(11_016), days_since_epoch("2000-02-29"));
+        assert_eq!(Some(20_744), days_since_epoch("2026-10-18"));
+        assert_eq!(Some(-1), days_since_epoch("1969-12-31"));
+        assert_eq!(None, days_since_epoch("2026-02-29"));
+        assert_eq!(None, days_since_epoch("2026-13-01"));
+        assert_eq!(None, days_since_epoch("18.10.2026"));
+        for days in [-719_468, -1, 0, 11_016, 20_744, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            let date = format!("{year:04}-{month:02}-{day:02}");
+            assert_eq!(Some(days), days_since_epoch(&date), "{date}");
+        }
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(Some(Temporal::date(20_744)), "2026-10-18".parse().ok());
+        let evening = Temporal::new(20_744.0 * DAY + 17.0 * HOUR + 45.0 * MINUTE, Kind::Date);
+        assert_eq!(Some(evening), "2026-10-18t17:45z".parse().ok());
+        assert_eq!(
+            Some(Temporal::new(63_930.5, Kind::Time)),
+            "17:45:30.5".parse().ok()
+        );
+        assert_eq!(Some(Temporal::duration(29_700.0)), "PT8H15M".parse().ok());
+        assert_eq!(Some(Temporal::duration(1_296_000.0)), "P2W1D".parse().ok());
+        assert_eq!(Some(Temporal::duration(DAY)), "days".parse().ok());
+        assert_eq!(Some(Temporal::number(90.0)), "90".parse().ok());
+        for invalid in [
+            "2026-02-30",
+            "24:00",
+            "7:45",
+            "PT",
+            "P1H",
+            "PT1D",
+            "P1DT",
+            "P1M",
+            "x",
+        ] {
+            assert!(invalid.parse::<Temporal>().is_err(), "{invalid}");
+        }
+
+        for value in [
+            "2027-01-16",
+            "2026-10-18T17:45",
+            "17:45:30.5",
+            "09:05",
+            "PT8H15M",
+        ] {
+            assert_eq!(value, value.parse::<Temporal>().unwrap().to_string());
+        }
+        assert_eq!("P1DT2H0.5S", Temporal::duration(DAY + 7200.5).to_string());
+        assert_eq!("-PT30M", Temporal::duration(-1800.0).to_string());
+        assert_eq!("PT0S", Temporal::duration(0.0).to_string());
+    }
+
+    #[test]
+    fn test_calculate() {
+        assert_eq!(
+            Ok("2027-01-16".to_owned()),
+            calculate("2026-10-18 + 90 days")
+        );
+        assert_eq!(Ok("PT8H15M".to_owned()), calculate("17:45 - 09:30"));
+        assert_eq!(Ok("P90D".to_owned()), calculate("2027-01-16 - 2026-10-18"));
+        assert_eq!(Ok("01:15".to_owned()), calculate("17:45 + 7.5 h"));
+        assert_eq!(
+            Ok("2026-10-17T22:00".to_owned()),
+            calculate("2026-10-18 - 2 hours")
+        );
+        assert_eq!(Ok("PT1H30M".to_owned()), calculate("3 * 30 min"));
+        assert_eq!(Ok("10:20".to_owned()), calculate("1 > 0 ? 10:20 : 11:00"));
+
+        // Invalid literals are errors, not differences of numbers.
+        for invalid in ["2026-13-01", "2026-02-30", "2026-10-18T24:00", "25:00 + 1"] {
+            assert!(
+                matches!(
+                    parse_term(invalid, &Temporal::constants()),
+                    Err(ParsingError::InvalidInput(_))
+                ),
+                "{invalid}"
+            );
+        }
+        assert_eq!(Ok("8.25".to_owned()), calculate("(17:45 - 09:30) / h"));
+        assert_eq!(Ok("1".to_owned()), calculate("2026-10-18 < 2026-10-19"));
+        assert_eq!(Ok("PT15M".to_owned()), calculate("PT8H15M mod h"));
+
+        assert_eq!(
+            Err(CalculationError::Undefined(
+                "2026-10-18 + 2026-10-19".to_owned()
+            )),
+            calculate("2026-10-18 + 2026-10-19")
+        );
+        assert_eq!(
+            Err(CalculationError::IncompatibleUnits(
+                "PT1H".to_owned(),
+                "2026-10-18".to_owned()
+            )),
+            calculate("PT1H - 2026-10-18")
+        );
+        assert!(calculate("2026-10-18 * 2").is_err());
+        assert!(calculate("sin(17:45)").is_err());
+        assert_eq!(Err(CalculationError::DivisionByZero), calculate("P1D / 0"));
+    }
+}
@@ -0,0 +1,272 @@
+//! Symbolic differentiation of parsed terms.
+
+use crate::{
+    calculate::{CalculationError, variable_name},
+    simplify::simplify,
+    token::{Function, Operator, Token},
+    traits::Numeric,
+    tree::{NodeId, Tree},
+};
+
+/// Writes the derivative of subtrees of `term` into `out`. Parts of `term`
+/// that the derivative needs unchanged are copied over.
+struct Differentiator<'a, T: Numeric> {
+    term: &'a Tree<Token<T>>,
+    var: &'a str,
+    out: Tree<Token<T>>,
+    /// Whether the subtree at a node of `term` contains the variable.
+    depends: Vec<bool>,
+}
+
+impl<'a, T: Numeric> Differentiator<'a, T> {
+    fn new(term: &'a Tree<Token<T>>, k: NodeId, var: &'a str) -> Self {
+        let mut depends = vec![false; term.len()];
+        term.traverse_postorder(k, &mut |(id, token)| {
+            depends[id] = matches!(token, Token::Var(name) if name == var)
+                || term.children(id).iter().any(|&child| depends[child]);
+        });
+        Differentiator {
+            term,
+            var,
+            out: Tree::new(),
+            depends,
+        }
+    }
+
+    fn value(&mut self, v: T) -> NodeId {
+        self.out.push(Token::Value(v), vec![])
+    }
+
+    fn op(&mut self, op: Operator, children: Vec<NodeId>) -> NodeId {
+        self.out.push(Token::Op(op), children)
+    }
+
+    fn func(&mut self, func: Function, argument: NodeId) -> NodeId {
+        self.out.push(Token::Func(func), vec![argument])
+    }
+
+    fn copy(&mut self, k: NodeId) -> NodeId {
+        self.out.graft(self.term, k)
+    }
+
+    /// Derivative of the subtree at node `k` of `term`, unsimplified.
+    fn d(&mut self, k: NodeId) -> Result<NodeId, CalculationError> {
+        if !self.depends.get(k).copied().unwrap_or_default() {
+            return Ok(self.value(T::default()));
+        }
+        let term = self.term;
+        let token = term
+            .get(k)
+            .ok_or_else(|| CalculationError::MissingOperand(String::new()))?;
+
+        match (token, term.children(k)) {
+            (Token::Var(_), _) => Ok(self.value(T::one())),
+            (Token::Op(op @ (Operator::ADD | Operator::SUB)), &[a, b]) => {
+                let (da, db) = (self.d(a)?, self.d(b)?);
+                Ok(self.op(*op, vec![da, db]))
+            }
+            (Token::Op(Operator::NEG), &[a]) => {
+                let da = self.d(a)?;
+                Ok(self.op(Operator::NEG, vec![da]))
+            }
+            // Product rule, (ab)' = a'b + ab'
+            (Token::Op(Operator::MUL), &[a, b]) => {
+                let (da, b_) = (self.d(a)?, self.copy(b));
+                let (a_, db) = (self.copy(a), self.d(b)?);
+                let left = self.op(Operator::MUL, vec![da, b_]);
+                let right = self.op(Operator::MUL, vec![a_, db]);
+                Ok(self.op(Operator::ADD, vec![left, right]))
+            }
+            // Quotient rule, (a / b)' = (a'b - ab') / b^2
+            (Token::Op(Operator::DIV), &[a, b]) => {
+                let (da, b_) = (self.d(a)?, self.copy(b));
+                let (a_, db) = (self.copy(a), self.d(b)?);
+                let left = self.op(Operator::MUL, vec![da, b_]);
+                let right = self.op(Operator::MUL, vec![a_, db]);
+                let numerator = self.op(Operator::SUB, vec![left, right]);
+                let b_ = self.copy(b);
+                let two = self.value(T::one() + T::one());
+                let denominator = self.op(Operator::POW, vec![b_, two]);
+                Ok(self.op(Operator::DIV, vec![numerator, denominator]))
+            }
+            // (a^n)' = n a^(n - 1) a' for a constant exponent
+            (Token::Op(Operator::POW), &[a, n]) if !self.depends[n] => {
+                let n_ = self.copy(n);
+                let one = self.value(T::one());
+                let exponent = self.op(Operator::SUB, vec![n_, one]);
+                let a_ = self.copy(a);
+                let power = self.op(Operator::POW, vec![a_, exponent]);
+                let n_ = self.copy(n);
+                let outer = self.op(Operator::MUL, vec![n_, power]);
+                let da = self.d(a)?;
+                Ok(self.op(Operator::MUL, vec![outer, da]))
+            }
+            // (a^b)' = a^b (b' ln(a) + b a' / a)
+            (Token::Op(Operator::POW), &[a, b]) => {
+                let power = self.copy(k);
+                let db = self.d(b)?;
+                let a_ = self.copy(a);
+                let ln = self.func(Function::LN, a_);
+                let left = self.op(Operator::MUL, vec![db, ln]);
+                let right = match self.depends[a] {
+                    true => {
+                        let b_ = self.copy(b);
+                        let da = self.d(a)?;
+                        let product = self.op(Operator::MUL, vec![b_, da]);
+                        let a_ = self.copy(a);
+                        self.op(Operator::DIV, vec![product, a_])
+                    }
+                    false => self.value(T::default()),
+                };
+                let inner = self.op(Operator::ADD, vec![left, right]);
+                Ok(self.op(Operator::MUL, vec![power, inner]))
+            }
+            // Each branch on its own, the derivative is undefined where the condition changes.
+            (Token::Op(Operator::COND), &[c, a, b]) => {
+                let c_ = self.copy(c);
+                let (da, db) = (self.d(a)?, self.d(b)?);
+                Ok(self.op(Operator::COND, vec![c_, da, db]))
+            }
+            (Token::Func(Function::DIFF), &[f, x]) => {
+                let var = variable_name(term, x, Function::DIFF)?;
+                let inner = derivative_at(term, f, var)?;
+                let outer = diff(&inner, self.var)?;
+                Ok(match outer.root() {
+                    Some(root) => self.out.graft(&outer, root),
+                    None => self.value(T::default()),
+                })
+            }
+            (Token::Func(Function::EXPAND), &[a]) => self.d(a),
+            // Chain rule, f(a)' = f'(a) a'
+            (Token::Func(func), &[a]) => {
+                let a_ = self.copy(a);
+                let outer = match func {
+                    Function::SIN => self.func(Function::COS, a_),
+                    Function::COS => {
+                        let sin = self.func(Function::SIN, a_);
+                        self.op(Operator::NEG, vec![sin])
+                    }
+                    Function::TAN => {
+                        let cos = self.func(Function::COS, a_);
+                        let two = self.value(T::one() + T::one());
+                        let square = self.op(Operator::POW, vec![cos, two]);
+                        let one = self.value(T::one());
+                        self.op(Operator::DIV, vec![one, square])
+                    }
+                    Function::EXP => self.func(Function::EXP, a_),
+                    Function::LN => {
+                        let one = self.value(T::one());
+                        self.op(Operator::DIV, vec![one, a_])
+                    }
+                    Function::SQRT => {
+                        let sqrt = self.func(Function::SQRT, a_);
+                        let two = self.value(T::one() + T::one());
+                        let denominator = self.op(Operator::MUL, vec![two, sqrt]);
+                        let one = self.value(T::one());
+                        self.op(Operator::DIV, vec![one, denominator])
+                    }
+                    other => return Err(CalculationError::NotDifferentiable(other.to_string())),
+                };
+                let da = self.d(a)?;
+                Ok(self.op(Operator::MUL, vec![outer, da]))
+            }
+            (other, _) => Err(CalculationError::NotDifferentiable(other.to_string())),
+        }
+    }
+}
+
+/// Simplified derivative of the subtree at node `k` with respect to the variable `var`.
+pub fn derivative_at<T: Numeric>(
+    term: &Tree<Token<T>>,
+    k: NodeId,
+    var: &str,
+) -> Result<Tree<Token<T>>, CalculationError> {
+    let mut differentiator = Differentiator::new(term, k, var);
+    let root = differentiator.d(k)?;
+    differentiator.out.set_root(root);
+    Ok(simplify(&differentiator.out))
+}
+
+/// Simplified derivative of a term with respect to the variable `var`. Sums, products,
+/// quotients, powers, conditionals and the elementary functions can be differentiated,
+/// other operations only if they don't depend on `var`.
+pub fn diff<T: Numeric>(
+    term: &Tree<Token<T>>,
+    var: &str,
+) -> Result<Tree<Token<T>>, CalculationError> {
+    match term.root() {
+        Some(root) => derivative_at(term, root, var),
+        None => Ok(Tree::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate::{Variables, calculate_with_variables},
+        parse::parse_term,
+        traits::Constants,
+    };
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    fn derivative(s: &str) -> String {
+        diff(&parse_term(s, &CONSTANTS).unwrap(), "x")
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!("0", derivative("y^2 + gcd(4, 6)"));
+        assert_eq!("3 * x^2 + 2", derivative("x^3 + 2x - 7"));
+        assert_eq!("(2 * x + 1) * y", derivative("(x + 1) * x * y"));
+        assert_eq!("2 * cos(2 * x)", derivative("sin(2x)"));
+        assert_eq!("-exp(cos(x)) * sin(x)", derivative("exp(cos(x))"));
+        assert_eq!("x > 0 ? 1 : -1", derivative("x > 0 ? x : -x"));
+        assert_eq!("12 * x", derivative("diff(x^3, x) * 2"));
+        assert_eq!(
+            Err(CalculationError::NotDifferentiable("!".to_owned())),
+            diff(&parse_term("x!", &CONSTANTS).unwrap(), "x")
+        );
+    }
+
+    #[test]
+    fn test_numeric_agreement() {
+        // Compares the derivative with a central difference quotient.
+        for term in [
+            "x / (1 + x^2)",
+            "sqrt(x) * ln(x)",
+            "tan(x)^2 - 1 / x",
+            "x^x",
+            "2^x * e^(-x)",
+        ] {
+            let tree = parse_term(term, &CONSTANTS).unwrap();
+            let derivative = diff(&tree, "x").unwrap();
+            let at = |t: &Tree<Token<f64>>, x: f64| {
+                calculate_with_variables(t, &Variables::from([("x".to_owned(), x)])).unwrap()
+            };
+            let h = 1e-6;
+            let quotient = (at(&tree, 0.7 + h) - at(&tree, 0.7 - h)) / (2.0 * h);
+            assert!((at(&derivative, 0.7) - quotient).abs() < 1e-6, "{term}");
+        }
+    }
+
+    #[test]
+    fn test_diff_function() {
+        let variables = Variables::from([("x".to_owned(), 3.0)]);
+        let calc =
+            |s: &str| calculate_with_variables(&parse_term(s, &CONSTANTS).unwrap(), &variables);
+
+        assert_eq!(Ok(27.0), calc("diff(x^3, x)"));
+        assert_eq!(Ok(18.0), calc("diff(diff(x^3, x), x)"));
+        assert_eq!(
+            Err(CalculationError::ExpectedVariable(Function::DIFF)),
+            calc("diff(x^2, 2)")
+        );
+    }
+}
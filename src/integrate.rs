@@ -0,0 +1,312 @@
+//! Numerical integration of parsed terms with adaptive Gauss-Kronrod quadrature.
+
+use crate::{
+    calculate::{CalculationError, Variables, calculate_subtree},
+    token::{Function, Token},
+    traits::Numeric,
+    tree::{NodeId, Tree},
+};
+
+/// Nodes of the 15 point Kronrod rule on `[-1, 1]`, the odd ones are the 7 point Gauss nodes.
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_18,
+    0.140_653_259_715_525_92,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_83,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+/// When to stop refining an integral.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Accepted error regardless of the size of the integral.
+    pub absolute: f64,
+    /// Accepted error relative to the size of the integral.
+    pub relative: f64,
+    /// Number of subintervals after which the integration gives up.
+    pub max_intervals: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            absolute: 1e-10,
+            relative: 1e-10,
+            max_intervals: 1000,
+        }
+    }
+}
+
+impl Tolerance {
+    /// The default tolerance, or a thousand times `epsilon` for arithmetic that
+    /// rounds too coarsely to reach it, like `f32`.
+    pub fn for_epsilon(epsilon: f64) -> Self {
+        let default = Tolerance::default();
+        Tolerance {
+            absolute: default.absolute.max(1e3 * epsilon),
+            relative: default.relative.max(1e3 * epsilon),
+            ..default
+        }
+    }
+
+    pub(crate) fn accepts(&self, integral: &Integral) -> bool {
+        integral.error <= self.absolute.max(self.relative * integral.value.abs())
+    }
+}
+
+/// Value of a definite integral with an estimate of its absolute error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Integral {
+    pub value: f64,
+    pub error: f64,
+}
+
+struct Interval {
+    a: f64,
+    b: f64,
+    integral: Integral,
+}
+
+/// Evaluates the subtree at node `k` with `var` bound to the given points.
+struct Integrand<'a, T: Numeric> {
+    term: &'a Tree<Token<T>>,
+    k: NodeId,
+    var: &'a str,
+    variables: Variables<T>,
+}
+
+impl<T: Numeric> Integrand<'_, T> {
+    fn at(&mut self, x: f64) -> Result<f64, CalculationError> {
+        let undefined = || CalculationError::Undefined(Function::INTEGRATE.to_string());
+        let x = T::from_f64(x).ok_or_else(undefined)?;
+        self.variables.insert(self.var.to_owned(), x);
+        let y = calculate_subtree(self.term, self.k, &self.variables)?.to_f64();
+        match y.is_finite() {
+            true => Ok(y),
+            false => Err(undefined()),
+        }
+    }
+
+    /// 15 point Kronrod rule on `[a, b]`, the difference to the embedded
+    /// 7 point Gauss rule estimates the error.
+    fn kronrod(&mut self, a: f64, b: f64) -> Result<Interval, CalculationError> {
+        let center = (a + b) / 2.0;
+        let half = (b - a) / 2.0;
+        let f_center = self.at(center)?;
+        let mut kronrod = f_center * KRONROD_WEIGHTS[7];
+        let mut gauss = f_center * GAUSS_WEIGHTS[3];
+        for j in 0..7 {
+            let dx = half * KRONROD_NODES[j];
+            let sum = self.at(center - dx)? + self.at(center + dx)?;
+            kronrod += KRONROD_WEIGHTS[j] * sum;
+            if j % 2 == 1 {
+                gauss += GAUSS_WEIGHTS[j / 2] * sum;
+            }
+        }
+        Ok(Interval {
+            a,
+            b,
+            integral: Integral {
+                value: kronrod * half,
+                error: ((kronrod - gauss) * half).abs(),
+            },
+        })
+    }
+}
+
+/// Splits the interval with the largest error until the total error is within
+/// the tolerance or the interval budget is spent. The integrand is never
+/// evaluated at the bounds themselves.
+fn integrate_adaptive<T: Numeric>(
+    integrand: &mut Integrand<T>,
+    a: f64,
+    b: f64,
+    tolerance: &Tolerance,
+) -> Result<Integral, CalculationError> {
+    let mut intervals = vec![integrand.kronrod(a, b)?];
+    loop {
+        let total = intervals.iter().fold(
+            Integral {
+                value: 0.0,
+                error: 0.0,
+            },
+            |total, interval| Integral {
+                value: total.value + interval.integral.value,
+                error: total.error + interval.integral.error,
+            },
+        );
+        if tolerance.accepts(&total) || intervals.len() >= tolerance.max_intervals {
+            return Ok(total);
+        }
+
+        let (worst, _) = intervals
+            .iter()
+            .enumerate()
+            .max_by(|(_, x), (_, y)| x.integral.error.total_cmp(&y.integral.error))
+            .expect("there is at least one interval");
+        let Interval { a, b, .. } = intervals.swap_remove(worst);
+        let middle = (a + b) / 2.0;
+        intervals.push(integrand.kronrod(a, middle)?);
+        intervals.push(integrand.kronrod(middle, b)?);
+    }
+}
+
+/// Definite integral of the subtree at node `k` over `var` from `a` to `b`.
+/// Other variables of the term take their values from `variables`.
+pub(crate) fn integral_at<T: Numeric>(
+    term: &Tree<Token<T>>,
+    k: NodeId,
+    var: &str,
+    (a, b): (f64, f64),
+    variables: &Variables<T>,
+    tolerance: &Tolerance,
+) -> Result<Integral, CalculationError> {
+    let mut integrand = Integrand {
+        term,
+        k,
+        var,
+        variables: variables.clone(),
+    };
+    integrate_adaptive(&mut integrand, a, b, tolerance)
+}
+
+/// Definite integral of a term in the single variable `var` from `a` to `b`.
+/// The result is returned even if the error estimate exceeds the tolerance,
+/// callers decide with [`Integral::error`] whether it is good enough.
+pub fn integrate<T: Numeric>(
+    term: &Tree<Token<T>>,
+    var: &str,
+    a: f64,
+    b: f64,
+    tolerance: &Tolerance,
+) -> Result<Integral, CalculationError> {
+    match term.root() {
+        Some(root) => integral_at(term, root, var, (a, b), &Variables::new(), tolerance),
+        None => Err(CalculationError::MissingOperand(
+            Function::INTEGRATE.to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate::{calculate_result, calculate_with_variables},
+        parse::parse_term,
+        traits::Constants,
+    };
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    fn integral(s: &str, a: f64, b: f64) -> Integral {
+        let term = parse_term(s, &CONSTANTS).unwrap();
+        integrate(&term, "x", a, b, &Tolerance::default()).unwrap()
+    }
+
+    #[test]
+    fn test_integrate() {
+        let cases = [
+            ("x^2", 0.0, 1.0, 1.0 / 3.0),
+            ("sin(x)", 0.0, std::f64::consts::PI, 2.0),
+            ("exp(-x^2)", -10.0, 10.0, std::f64::consts::PI.sqrt()),
+            ("1 / x", 1.0, std::f64::consts::E, 1.0),
+            ("sqrt(x)", 0.0, 4.0, 16.0 / 3.0),
+            ("x < 1 ? 1 : 3", 0.0, 2.0, 4.0),
+            ("x", 1.0, 0.0, -0.5),
+        ];
+        for (term, a, b, expected) in cases {
+            let result = integral(term, a, b);
+            assert!((result.value - expected).abs() < 1e-8, "{term}: {result:?}");
+            assert!(result.error < 1e-8, "{term}: {result:?}");
+        }
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let term = parse_term("ln(x)", &CONSTANTS).unwrap();
+        let coarse = Tolerance {
+            absolute: 1e-3,
+            relative: 0.0,
+            max_intervals: 1000,
+        };
+        let coarse = integrate(&term, "x", 0.0, 1.0, &coarse).unwrap();
+        let fine = integrate(&term, "x", 0.0, 1.0, &Tolerance::default()).unwrap();
+        assert!(coarse.error <= 1e-3 && fine.error < coarse.error);
+        assert!((fine.value + 1.0).abs() < 1e-9);
+
+        // A single interval can't resolve the jump.
+        let term = parse_term("x < 0.3 ? 0 : 1", &CONSTANTS).unwrap();
+        let single = Tolerance {
+            max_intervals: 1,
+            ..Tolerance::default()
+        };
+        assert!(integrate(&term, "x", 0.0, 1.0, &single).unwrap().error > 1e-3);
+    }
+
+    #[test]
+    fn test_integrate_function() {
+        let calc = |s: &str| calculate_result(&parse_term(s, &CONSTANTS).unwrap());
+
+        assert!((calc("integrate(x^3, x, 0, 2)").unwrap() - 4.0).abs() < 1e-9);
+        assert!((calc("2 * integrate(cos(t), t, 0, pi / 2)").unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(
+            Err(CalculationError::ExpectedVariable(Function::INTEGRATE)),
+            calc("integrate(x, 2, 0, 1)")
+        );
+        assert_eq!(
+            Err(CalculationError::DivisionByZero),
+            calc("integrate(1 / (x - x), x, 0, 1)")
+        );
+
+        let variables = Variables::from([("a".to_owned(), 3.0)]);
+        let term = parse_term("integrate(a * x, x, 0, a)", &CONSTANTS).unwrap();
+        let result = calculate_with_variables(&term, &variables).unwrap();
+        assert!((result - 13.5).abs() < 1e-9);
+
+        // Integer types can't bind the variable to the quadrature points.
+        let term = parse_term::<i64>("integrate(2 * x, x, 0, 3)", &Constants { e: 3, pi: 3 });
+        assert_eq!(
+            Err(CalculationError::Undefined("integrate".to_owned())),
+            calculate_result(&term.unwrap())
+        );
+    }
+
+    #[test]
+    fn test_integrate_f32() {
+        let constants = Constants::<f32> {
+            e: std::f32::consts::E,
+            pi: std::f32::consts::PI,
+        };
+        let calc = |s: &str| calculate_result(&parse_term(s, &constants).unwrap());
+        assert!((calc("integrate(x^2, x, 0, 1)").unwrap() - 1.0 / 3.0).abs() < 1e-6);
+        assert!((calc("integrate(sin(x), x, 0, pi)").unwrap() - 2.0).abs() < 1e-5);
+        assert!((calc("integrate(ln(x), x, 0, 1)").unwrap() + 1.0).abs() < 1e-4);
+
+        let tolerance = Tolerance::for_epsilon(f64::EPSILON);
+        assert_eq!(Tolerance::default(), tolerance);
+        assert!(Tolerance::for_epsilon(f32::EPSILON.into()).relative > 1e-5);
+    }
+}
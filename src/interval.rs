@@ -0,0 +1,736 @@
+//! Interval arithmetic, every value is a range `[lo, hi]` that contains the exact result.
+
+use std::{
+    cmp::Ordering,
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+
+use crate::traits::{
+    Checked, Combinatorics, Commensurable, Constants, Division, Elementary, FromBinaryStr,
+    FromHexStr, FromScientificStr, FromStrError, FromTemporalStr, Numeric, One, Real,
+};
+
+/// Floating point types for the bounds of an [`Interval`] and the values of an
+/// [`Uncertain`](crate::uncertain::Uncertain) or a [`Quantity`](crate::units::Quantity).
+pub trait Endpoint: Numeric {
+    const INFINITY: Self;
+    const NAN: Self;
+    const E: Self;
+    const PI: Self;
+    /// Largest integer up to which every integer is exactly representable.
+    const MAX_EXACT: Self;
+    fn next_down(self) -> Self;
+    fn next_up(self) -> Self;
+    /// `self * a + b` with a single rounding.
+    fn mul_add(self, a: Self, b: Self) -> Self;
+    fn floor(self) -> Self;
+    fn trunc(self) -> Self;
+}
+
+macro_rules! impl_endpoint {
+    ($($t:ident => $max_exact:expr),*) => {$(
+        impl Endpoint for $t {
+            const INFINITY: Self = <$t>::INFINITY;
+            const NAN: Self = <$t>::NAN;
+            const E: Self = std::$t::consts::E;
+            const PI: Self = std::$t::consts::PI;
+            const MAX_EXACT: Self = $max_exact;
+            fn next_down(self) -> Self {
+                <$t>::next_down(self)
+            }
+            fn next_up(self) -> Self {
+                <$t>::next_up(self)
+            }
+            fn mul_add(self, a: Self, b: Self) -> Self {
+                <$t>::mul_add(self, a, b)
+            }
+            fn floor(self) -> Self {
+                <$t>::floor(self)
+            }
+            fn trunc(self) -> Self {
+                <$t>::trunc(self)
+            }
+        }
+    )*};
+}
+
+impl_endpoint!(f32 => 16_777_216.0, f64 => 9_007_199_254_740_992.0);
+
+fn is_infinite<T: Endpoint>(x: T) -> bool {
+    x == T::INFINITY || x == -T::INFINITY
+}
+
+/// Rounds `x`, which differs from the exact result by `error`, down to a lower bound.
+/// Overflows have no exact error and are always rounded.
+fn down<T: Endpoint>(x: T, error: T) -> T {
+    if error < T::default() || is_infinite(x) {
+        x.next_down()
+    } else {
+        x
+    }
+}
+
+/// Rounds `x`, which differs from the exact result by `error`, up to an upper bound.
+fn up<T: Endpoint>(x: T, error: T) -> T {
+    if error > T::default() || is_infinite(x) {
+        x.next_up()
+    } else {
+        x
+    }
+}
+
+/// Exact rounding error of `s = a + b` (TwoSum).
+fn sum_error<T: Endpoint>(a: T, b: T, s: T) -> T {
+    let b_rounded = s - a;
+    (a - (s - b_rounded)) + (b - b_rounded)
+}
+
+/// Exact rounding error of `p = a * b`, the fused multiply-add doesn't round the product.
+fn product_error<T: Endpoint>(a: T, b: T, p: T) -> T {
+    a.mul_add(b, -p)
+}
+
+/// Sign of the rounding error of `q = a / b` from the exact remainder `a - q * b`.
+fn quotient_error<T: Endpoint>(a: T, b: T, q: T) -> T {
+    let remainder = (-q).mul_add(b, a);
+    if b < T::default() {
+        -remainder
+    } else {
+        remainder
+    }
+}
+
+fn mul_down<T: Endpoint>(a: T, b: T) -> T {
+    let p = a * b;
+    down(p, product_error(a, b, p))
+}
+
+fn mul_up<T: Endpoint>(a: T, b: T) -> T {
+    let p = a * b;
+    up(p, product_error(a, b, p))
+}
+
+/// `x^n` for `x >= 0` by squaring, with every product rounded by `mul`.
+fn power_by_squaring<T: Endpoint>(mut x: T, mut n: u64, mul: fn(T, T) -> T) -> T {
+    let mut result = T::one();
+    while n > 0 {
+        if n % 2 == 1 {
+            result = mul(result, x);
+        }
+        x = mul(x, x);
+        n /= 2;
+    }
+    result
+}
+
+fn min<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a { b } else { a }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a { b } else { a }
+}
+
+/// A closed range of real numbers. Every operation rounds its bounds outwards,
+/// so the result contains the exact result for every choice of values from the operands.
+///
+/// The basic operations and `sqrt` round their bounds exactly, so they keep single
+/// numbers as they are if the result is representable. The other functions are computed
+/// in floating point at the bounds and widened by one unit in the last place.
+///
+/// Comparisons are certain: `a < b` holds if every value of `a` is less than every value
+/// of `b`, overlapping intervals are unordered. `==` compares the bounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interval<T> {
+    lo: T,
+    hi: T,
+}
+
+impl<T: Endpoint> Interval<T> {
+    /// Interval from `lo` to `hi`, `None` if `lo > hi` or a bound is NaN.
+    pub fn new(lo: T, hi: T) -> Option<Self> {
+        (lo <= hi).then_some(Interval { lo, hi })
+    }
+
+    /// Interval that only contains `x`.
+    pub fn point(x: T) -> Self {
+        Interval { lo: x, hi: x }
+    }
+
+    /// Smallest interval around `x`, which is off from the exact value by less than one
+    /// unit in the last place.
+    fn around(x: T) -> Self {
+        Interval {
+            lo: x.next_down(),
+            hi: x.next_up(),
+        }
+    }
+
+    /// Result of an integer valued function, which is exact unless it is too large.
+    fn integral(x: T) -> Self {
+        match x.floor() == x && -T::MAX_EXACT <= x && x <= T::MAX_EXACT {
+            true => Self::point(x),
+            false => Self::around(x),
+        }
+    }
+
+    /// Smallest interval that contains both.
+    fn hull(self, other: Self) -> Self {
+        Interval {
+            lo: min(self.lo, other.lo),
+            hi: max(self.hi, other.hi),
+        }
+    }
+
+    /// Interval from `lo` around `lo` to `hi` around `hi`.
+    fn between(lo: T, hi: T) -> Self {
+        Self::around(lo).hull(Self::around(hi))
+    }
+
+    pub fn lo(&self) -> T {
+        self.lo
+    }
+
+    pub fn hi(&self) -> T {
+        self.hi
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    /// `e` and `pi` enclosed by their neighbouring floats.
+    pub fn constants() -> Constants<Self> {
+        Constants {
+            e: Self::around(T::E),
+            pi: Self::around(T::PI),
+        }
+    }
+
+    fn value(&self) -> Option<T> {
+        (self.lo == self.hi).then_some(self.lo)
+    }
+
+    fn abs(self) -> Self {
+        let zero = T::default();
+        match () {
+            _ if self.lo >= zero => self,
+            _ if self.hi <= zero => -self,
+            _ => Interval {
+                lo: zero,
+                hi: max(-self.lo, self.hi),
+            },
+        }
+    }
+
+    fn finite(self) -> Option<Self> {
+        (!is_infinite(self.lo) && !is_infinite(self.hi)).then_some(self)
+    }
+
+    /// `self^n` for an integer `n`.
+    fn integer_power(self, n: T) -> Option<Self> {
+        let zero = T::default();
+        if n < zero {
+            if self.contains(zero) {
+                return None;
+            }
+            return (Self::one() / self.integer_power(-n)?).finite();
+        }
+        let n = n.to_f64() as u64;
+        let power_down = |x| power_by_squaring(x, n, mul_down);
+        let power_up = |x| power_by_squaring(x, n, mul_up);
+        let power = match () {
+            _ if self.lo >= zero => Interval {
+                lo: power_down(self.lo),
+                hi: power_up(self.hi),
+            },
+            // An odd power keeps the sign, an even one is the power of the absolute value.
+            _ if n % 2 == 1 => Interval {
+                lo: -power_up(-self.lo),
+                hi: if self.hi >= zero {
+                    power_up(self.hi)
+                } else {
+                    -power_down(-self.hi)
+                },
+            },
+            _ if self.hi <= zero => Interval {
+                lo: power_down(-self.hi),
+                hi: power_up(-self.lo),
+            },
+            _ => Interval {
+                lo: zero,
+                hi: max(power_up(-self.lo), power_up(self.hi)),
+            },
+        };
+        power.finite()
+    }
+
+    /// Range of a periodic function like `sin` on the interval, `peak` is the position of
+    /// its maximum in `[0, 2pi)`, the minimum lies half a period after it.
+    fn periodic(self, f: fn(T) -> Option<T>, peak: f64) -> Option<Self> {
+        let (lo, hi) = (self.lo.to_f64(), self.hi.to_f64());
+        let one = T::one();
+        if hi - lo >= TAU {
+            return Some(Interval { lo: -one, hi: one });
+        }
+        let reaches = |x: f64| x + ((lo - x) / TAU).ceil() * TAU <= hi;
+        let range = Self::between(f(self.lo)?, f(self.hi)?);
+        Some(Interval {
+            lo: if reaches(peak + PI) {
+                -one
+            } else {
+                max(range.lo, -one)
+            },
+            hi: if reaches(peak) {
+                one
+            } else {
+                min(range.hi, one)
+            },
+        })
+    }
+}
+
+impl<T: Endpoint> Add for Interval<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (lo, hi) = (self.lo + rhs.lo, self.hi + rhs.hi);
+        Interval {
+            lo: down(lo, sum_error(self.lo, rhs.lo, lo)),
+            hi: up(hi, sum_error(self.hi, rhs.hi, hi)),
+        }
+    }
+}
+
+impl<T: Endpoint> Sub for Interval<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<T: Endpoint> Neg for Interval<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+impl<T: Endpoint> Mul for Interval<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let corners = [
+            (self.lo, rhs.lo),
+            (self.lo, rhs.hi),
+            (self.hi, rhs.lo),
+            (self.hi, rhs.hi),
+        ];
+        Interval {
+            lo: corners
+                .map(|(a, b)| mul_down(a, b))
+                .into_iter()
+                .reduce(min)
+                .unwrap(),
+            hi: corners
+                .map(|(a, b)| mul_up(a, b))
+                .into_iter()
+                .reduce(max)
+                .unwrap(),
+        }
+    }
+}
+
+/// A divisor that contains zero gives the whole real line.
+impl<T: Endpoint> Div for Interval<T> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        if rhs.contains(T::default()) {
+            return Interval {
+                lo: -T::INFINITY,
+                hi: T::INFINITY,
+            };
+        }
+        let corners = [
+            (self.lo, rhs.lo),
+            (self.lo, rhs.hi),
+            (self.hi, rhs.lo),
+            (self.hi, rhs.hi),
+        ];
+        let quotients = corners.map(|(a, b)| {
+            let q = a / b;
+            let error = quotient_error(a, b, q);
+            (down(q, error), up(q, error))
+        });
+        Interval {
+            lo: quotients.map(|(lo, _)| lo).into_iter().reduce(min).unwrap(),
+            hi: quotients.map(|(_, hi)| hi).into_iter().reduce(max).unwrap(),
+        }
+    }
+}
+
+impl<T: Endpoint> PartialOrd for Interval<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match () {
+            _ if self == other => Some(Ordering::Equal),
+            _ if self.hi < other.lo => Some(Ordering::Less),
+            _ if self.lo > other.hi => Some(Ordering::Greater),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Endpoint> One for Interval<T> {
+    fn one() -> Self {
+        Self::point(T::one())
+    }
+}
+
+/// Division by an interval that contains zero is undefined. The remainders are exact
+/// if the quotient is the same integer for all values, otherwise they are bounded by the divisor.
+impl<T: Endpoint> Division for Interval<T> {
+    fn quotient(self, rhs: Self) -> Option<Self> {
+        (!rhs.contains(T::default())).then(|| self / rhs)
+    }
+    fn floor_div(self, rhs: Self) -> Option<Self> {
+        let q = self.quotient(rhs)?;
+        Some(Interval {
+            lo: q.lo.floor(),
+            hi: q.hi.floor(),
+        })
+    }
+    fn modulo(self, rhs: Self) -> Option<Self> {
+        let rhs = rhs.abs();
+        match self.floor_div(rhs)?.value() {
+            Some(n) => Some(self - rhs * Self::point(n)),
+            None => Some(Interval {
+                lo: T::default(),
+                hi: rhs.hi,
+            }),
+        }
+    }
+    fn remainder(self, rhs: Self) -> Option<Self> {
+        let q = self.quotient(rhs)?;
+        let (lo, hi) = (q.lo.trunc(), q.hi.trunc());
+        if lo == hi {
+            return Some(self - rhs * Self::point(lo));
+        }
+        let (zero, bound) = (T::default(), rhs.abs().hi);
+        Some(Interval {
+            lo: if self.lo >= zero { zero } else { -bound },
+            hi: if self.hi <= zero { zero } else { bound },
+        })
+    }
+}
+
+impl<T: Endpoint> Checked for Interval<T> {}
+
+impl<T: Endpoint> FromTemporalStr for Interval<T> {}
+
+/// Factorials of intervals from `0.5` up, where the gamma function increases,
+/// the other functions only take single numbers.
+impl<T: Endpoint> Combinatorics for Interval<T> {
+    fn factorial(self) -> Option<Self> {
+        match self.value() {
+            Some(n) if n.floor() == n => n.factorial().map(Self::integral),
+            _ if self.lo.to_f64() >= 0.5 => {
+                Some(Self::between(self.lo.factorial()?, self.hi.factorial()?))
+            }
+            _ => None,
+        }
+    }
+    fn permutations(self, k: Self) -> Option<Self> {
+        self.value()?.permutations(k.value()?).map(Self::integral)
+    }
+    fn combinations(self, k: Self) -> Option<Self> {
+        self.value()?.combinations(k.value()?).map(Self::integral)
+    }
+    fn gcd(self, other: Self) -> Option<Self> {
+        self.value()?.gcd(other.value()?).map(Self::point)
+    }
+    fn lcm(self, other: Self) -> Option<Self> {
+        self.value()?.lcm(other.value()?).map(Self::integral)
+    }
+    fn is_prime(&self) -> Option<bool> {
+        self.value()?.is_prime()
+    }
+    fn prime_factors(self) -> Option<Vec<Self>> {
+        let factors = self.value()?.prime_factors()?;
+        Some(factors.into_iter().map(Self::point).collect())
+    }
+}
+
+impl<T: Endpoint> Elementary for Interval<T> {
+    /// Integer exponents take any base, otherwise the base can't be negative.
+    fn power(self, exponent: Self) -> Option<Self> {
+        let zero = T::default();
+        match exponent.value() {
+            Some(n) if n.floor() == n && max(n, -n) <= T::MAX_EXACT => self.integer_power(n),
+            _ if self.lo < zero || (self.lo == zero && exponent.lo <= zero) => None,
+            // x^y = exp(y * ln(x)) is monotone in both, so the corners are the extremes.
+            _ => {
+                let corners = [
+                    Self::between(self.lo.power(exponent.lo)?, self.lo.power(exponent.hi)?),
+                    Self::between(self.hi.power(exponent.lo)?, self.hi.power(exponent.hi)?),
+                ];
+                let power = corners[0].hull(corners[1]);
+                Some(Interval {
+                    lo: max(power.lo, zero),
+                    hi: power.hi,
+                })
+            }
+        }
+    }
+    fn root(self) -> Option<Self> {
+        if self.lo < T::default() {
+            return None;
+        }
+        let (lo, hi) = (self.lo.root()?, self.hi.root()?);
+        Some(Interval {
+            lo: down(lo, (-lo).mul_add(lo, self.lo)),
+            hi: up(hi, (-hi).mul_add(hi, self.hi)),
+        })
+    }
+    fn sine(self) -> Option<Self> {
+        self.periodic(Elementary::sine, FRAC_PI_2)
+    }
+    fn cosine(self) -> Option<Self> {
+        self.periodic(Elementary::cosine, 0.0)
+    }
+    /// Undefined if the interval contains a pole.
+    fn tangent(self) -> Option<Self> {
+        let (lo, hi) = (self.lo.to_f64(), self.hi.to_f64());
+        if hi - lo >= PI || FRAC_PI_2 + ((lo - FRAC_PI_2) / PI).ceil() * PI <= hi {
+            return None;
+        }
+        Some(Self::between(self.lo.tangent()?, self.hi.tangent()?))
+    }
+    fn exponential(self) -> Option<Self> {
+        let range = Self::between(self.lo.exponential()?, self.hi.exponential()?);
+        Some(Interval {
+            lo: max(range.lo, T::default()),
+            hi: range.hi,
+        })
+    }
+    fn logarithm(self) -> Option<Self> {
+        if self.lo <= T::default() {
+            return None;
+        }
+        Some(Self::between(self.lo.logarithm()?, self.hi.logarithm()?))
+    }
+}
+
+/// The numerical methods work with the midpoint.
+impl<T: Endpoint> Real for Interval<T> {
+    fn to_f64(self) -> f64 {
+        (self.lo.to_f64() + self.hi.to_f64()) / 2.0
+    }
+    fn from_f64(x: f64) -> Option<Self> {
+        let y = T::from_f64(x)?;
+        match y.to_f64() == x {
+            true => Some(Self::point(y)),
+            false => Some(Self::around(y)),
+        }
+    }
+    fn epsilon() -> f64 {
+        T::epsilon()
+    }
+}
+
+impl<T: Endpoint> Commensurable for Interval<T> {
+    fn commensurable(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T: Endpoint> FromBinaryStr for Interval<T> {
+    fn from_binary_str(s: &str) -> Result<Self, FromStrError> {
+        T::from_binary_str(s).map(Self::point)
+    }
+}
+
+impl<T: Endpoint> FromHexStr for Interval<T> {
+    fn from_hex_str(s: &str) -> Result<Self, FromStrError> {
+        T::from_hex_str(s).map(Self::point)
+    }
+}
+
+impl<T: Endpoint> FromScientificStr for Interval<T> {
+    fn from_scientific_str(s: &str) -> Result<Self, FromStrError> {
+        s.parse()
+    }
+}
+
+/// Reads `[1, 2]` as the interval between both numbers, `1.5±0.01` as the interval
+/// `0.01` around `1.5` and a single number as the interval that only contains it.
+impl<T: Endpoint> FromStr for Interval<T> {
+    type Err = FromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |s: &str| T::from_scientific_str(s.trim());
+        let empty = || anyhow!("{} is not an interval", s).into();
+
+        if let Some(bounds) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let (lo, hi) = bounds
+                .split_once(',')
+                .ok_or_else(|| anyhow!("{} is missing a ,", s))?;
+            return Self::new(number(lo)?, number(hi)?).ok_or_else(empty);
+        }
+        match s.split_once('±') {
+            Some((mid, radius)) => {
+                let radius = number(radius)?;
+                let deviation = Self::new(-radius, radius).ok_or_else(empty)?;
+                Ok(Self::point(number(mid)?) + deviation)
+            }
+            None => {
+                let x = number(s)?;
+                Self::new(x, x).ok_or_else(empty)
+            }
+        }
+    }
+}
+
+/// Single numbers are shown as they are, ranges as `[lo, hi]`.
+impl<T: Endpoint> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value() {
+            Some(x) => write!(f, "{}", x),
+            None => write!(f, "[{}, {}]", self.lo, self.hi),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate::{CalculationError, calculate_result},
+        parse::parse_term,
+    };
+
+    fn interval(lo: f64, hi: f64) -> Interval<f64> {
+        Interval::new(lo, hi).unwrap()
+    }
+
+    fn calculate(term: &str) -> Result<Interval<f64>, CalculationError> {
+        calculate_result(&parse_term(term, &Interval::constants()).unwrap())
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let (a, b) = (interval(1.0, 2.0), interval(-3.0, 4.0));
+        assert_eq!(interval(-2.0, 6.0), a + b);
+        assert_eq!(interval(-3.0, 5.0), a - b);
+        assert_eq!(interval(-6.0, 8.0), a * b);
+        assert_eq!(interval(0.25, 1.0), a / interval(2.0, 4.0));
+        assert_eq!(None, a.quotient(b));
+
+        // Exact results stay single numbers, rounded ones get the neighbouring floats.
+        assert_eq!(
+            Interval::point(3.0),
+            Interval::point(1.0) + Interval::point(2.0)
+        );
+        let sum = Interval::point(0.1) + Interval::point(0.2);
+        assert_eq!(interval(0.3, 0.30000000000000004), sum);
+        let third = Interval::point(1.0) / Interval::point(3.0);
+        assert_eq!(third.lo.next_up(), third.hi);
+        assert!(third.contains(1.0 / 3.0));
+
+        assert_eq!(
+            Some(interval(1.0, 1.0)),
+            Interval::point(-7.0).modulo(Interval::point(2.0))
+        );
+        assert_eq!(
+            Some(interval(0.0, 2.0)),
+            interval(1.0, 5.0).modulo(Interval::point(2.0))
+        );
+        assert_eq!(
+            Some(interval(-1.0, -1.0)),
+            Interval::point(-7.0).remainder(Interval::point(2.0))
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let a = interval(-1.0, 2.0);
+        assert_eq!(Some(interval(0.0, 4.0)), a.power(Interval::point(2.0)));
+        assert_eq!(Some(interval(-1.0, 8.0)), a.power(Interval::point(3.0)));
+        assert_eq!(None, a.power(Interval::point(-1.0)));
+        assert_eq!(None, a.power(Interval::point(0.5)));
+        assert_eq!(Some(interval(2.0, 3.0)), interval(4.0, 9.0).root());
+        assert_eq!(None, a.root());
+        assert_eq!(None, a.logarithm());
+        assert_eq!(
+            Some(interval(1.0, 1.0)),
+            interval(0.0, 0.0).power(Interval::point(0.0))
+        );
+
+        let sine = interval(0.0, 3.0).sine().unwrap();
+        assert_eq!(1.0, sine.hi);
+        assert!(sine.lo < 0.0 && sine.lo > -1e-300);
+        assert_eq!(Some(interval(-1.0, 1.0)), interval(0.0, 7.0).cosine());
+        assert_eq!(None, interval(1.0, 2.0).tangent());
+        let cosine = interval(3.0, 3.2).cosine().unwrap();
+        assert_eq!(-1.0, cosine.lo);
+
+        assert_eq!(
+            Some(interval(120.0, 120.0)),
+            Interval::point(5.0).factorial()
+        );
+        assert_eq!(
+            Some(interval(10.0, 10.0)),
+            Interval::point(5.0).combinations(Interval::point(2.0))
+        );
+        assert_eq!(None, interval(4.0, 5.0).gcd(Interval::point(2.0)));
+        assert_eq!(Some(true), Interval::point(7.0).is_prime());
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(interval(1.0, 2.0), "[1, 2]".parse().unwrap());
+        assert_eq!(interval(-1e3, 2.5), "[-1e3,2.5]".parse().unwrap());
+        assert_eq!(interval(1.0, 3.0), "2 ± 1".parse().unwrap());
+        assert_eq!(Interval::point(2.5), "2.5".parse().unwrap());
+        let tolerance: Interval<f64> = "1.5±0.01".parse().unwrap();
+        assert!(tolerance.contains(1.49) && tolerance.contains(1.51));
+        assert!("[2, 1]".parse::<Interval<f64>>().is_err());
+        assert!("1±-1".parse::<Interval<f64>>().is_err());
+
+        assert_eq!("[1, 2]", interval(1.0, 2.0).to_string());
+        assert_eq!("-0.5", Interval::point(-0.5).to_string());
+        assert!("[1, 2]".parse::<f64>().is_err());
+    }
+
+    #[test]
+    fn test_calculate() {
+        assert_eq!(Ok(interval(1.5, 4.0)), calculate("[1, 2] * 1.5 + 0.5±0.5"));
+        assert_eq!(Ok(interval(0.25, 4.0)), calculate("[1,4] / [1, 4]"));
+        assert_eq!(Ok(interval(-2.0, 4.0)), calculate("-[-4, 2]"));
+        assert_eq!(Ok(Interval::point(1.0)), calculate("[1, 2] < [3, 4]"));
+        assert_eq!(Ok(Interval::point(0.0)), calculate("[1, 3] < [2, 4]"));
+        assert_eq!(
+            Err(CalculationError::DivisionByZero),
+            calculate("1 / [-1, 1]")
+        );
+        let power = calculate("2^[3, 4]").unwrap();
+        assert!(power.contains(8.0) && power.contains(16.0) && power.hi - power.lo < 8.1);
+
+        let pi = calculate("pi").unwrap();
+        assert!(pi.lo < std::f64::consts::PI && std::f64::consts::PI < pi.hi);
+        let area = calculate("pi * (2±0.1)^2").unwrap();
+        assert!(area.contains(std::f64::consts::PI * 1.9 * 1.9));
+        assert!(area.contains(std::f64::consts::PI * 2.1 * 2.1));
+    }
+}
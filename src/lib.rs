@@ -0,0 +1,22 @@
+pub mod batch;
+pub mod calculate;
+pub mod compile;
+pub mod currency;
+pub mod datetime;
+pub mod derivative;
+pub mod integrate;
+pub mod interval;
+pub mod number_theory;
+pub mod parse;
+pub mod polynomial;
+pub mod render;
+pub mod rpn;
+pub mod sexpr;
+pub mod simplify;
+pub mod solve;
+pub mod token;
+pub mod traits;
+pub mod tree;
+pub mod uncertain;
+pub mod units;
+pub mod unparse;
@@ -1,17 +1,25 @@
-use calculate::calculate_result;
+use std::{env, sync::LazyLock};
+
 use iced::{
-    Alignment, Element, Length, Size, alignment, application, color,
-    widget::{Button, Column, Text, button, column, container, row, text, text_input},
+    Alignment, Element, Font, Size, application, color,
+    widget::{Button, Column, Text, button, column, container, row, scrollable, text, text_input},
+};
+use tree_calc::{
+    calculate::{CalculationError, calculate_result, factorize_result},
+    currency::{Rates, currency_result, is_currency_code},
+    datetime::{Temporal, has_literal, is_duration_word, today},
+    interval::Interval,
+    parse::{ParsingError, parse_term},
+    render::{to_ascii, to_dot},
+    rpn::{RpnStack, parse_rpn},
+    simplify::simplify,
+    solve::{SolveOptions, solve_result},
+    token::Token,
+    traits::{Constants, Numeric},
+    tree::Tree,
+    uncertain::Uncertain,
+    units::{Quantity, convert_result, is_unit},
 };
-use parse::parse_term;
-
-use crate::traits::Constants;
-
-mod calculate;
-mod parse;
-mod token;
-mod traits;
-mod tree;
 
 #[derive(Debug, Clone)]
 enum Message {
@@ -20,6 +28,11 @@ enum Message {
     Calculate,
     RemoveLast,
     Clear,
+    ToggleRpn,
+    /// Switches `±` between interval bounds and a standard deviation.
+    ToggleGaussian,
+    /// Word that is applied to the RPN stack right away.
+    Rpn(&'static str),
 }
 
 #[derive(Default)]
@@ -27,6 +40,9 @@ struct Calculator {
     input: String,
     stack: Vec<String>,
     error: Option<String>,
+    rpn: bool,
+    gaussian: bool,
+    rpn_stack: RpnStack<f32>,
 }
 
 static CONSTANTS: Constants<f32> = Constants::<f32> {
@@ -34,41 +50,198 @@ static CONSTANTS: Constants<f32> = Constants::<f32> {
     pi: std::f32::consts::PI,
 };
 
+static INTERVAL_CONSTANTS: LazyLock<Constants<Interval<f32>>> = LazyLock::new(Interval::constants);
+static UNCERTAIN_CONSTANTS: LazyLock<Constants<Uncertain<f32>>> =
+    LazyLock::new(Uncertain::constants);
+static UNIT_CONSTANTS: LazyLock<Constants<Quantity<f32>>> = LazyLock::new(Quantity::constants);
+static TEMPORAL_CONSTANTS: LazyLock<Constants<Temporal>> = LazyLock::new(Temporal::constants);
+/// Exchange rates from the file named by `TREE_CALC_RATES`, `rates.csv` by default.
+static RATES: LazyLock<Result<Rates, String>> = LazyLock::new(|| {
+    let path = env::var("TREE_CALC_RATES").unwrap_or_else(|_| "rates.csv".to_owned());
+    Rates::load(&path).map_err(|err| format!("{path}: {err}"))
+});
+
+/// Number type the algebraic input is evaluated with.
+enum Arithmetic {
+    Real,
+    Interval,
+    Gaussian,
+    Units,
+    Time,
+}
+
 pub fn keypad<Msg: Clone>(label: impl Into<String>) -> Button<'static, Msg> {
     let text = Text::new(label.into()).size(18).align_x(Alignment::Center);
 
     button(text).width(64).padding([12, 16])
 }
 
+/// Evaluates a parsed term to the text that replaces the input.
+/// A factorization is shown as the product of its prime factors, the roots of
+/// an equation as alternatives and a term with variables in its simplified form.
+fn evaluate<T: Numeric>(term: &Tree<Token<T>>) -> Result<String, CalculationError> {
+    if let Some((var, roots)) = solve_result(term, &SolveOptions::default())? {
+        return Ok(match roots.is_empty() {
+            true => format!("no solution for {var}"),
+            false => roots
+                .iter()
+                .map(|root| format!("{var} = {root}"))
+                .collect::<Vec<_>>()
+                .join(" or "),
+        });
+    }
+    if let Some((value, unit)) = convert_result(term)? {
+        return Ok(format!("{value} {unit}"));
+    }
+    match factorize_result(term)? {
+        Some(factors) if factors.is_empty() => Ok("1".to_owned()),
+        Some(factors) => Ok(factors
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>()
+            .join(" * ")),
+        None => match calculate_result(term) {
+            Err(CalculationError::UnknownVariable(_)) => Ok(simplify(term).to_string()),
+            result => result.map(|result| result.to_string()),
+        },
+    }
+}
+
+/// Parses and evaluates the input to its normalized form and the text of its result.
+fn calculate_input<T: Numeric>(
+    input: &str,
+    constants: &Constants<T>,
+) -> Result<(String, String), String> {
+    let term = parse_term(input, constants).map_err(|err| err.to_string())?;
+    let result = evaluate(&term).map_err(|err| err.to_string())?;
+    Ok((term.to_string(), result))
+}
+
+/// Like [`calculate_input`] for quantities, currency codes are converted with [`RATES`].
+fn calculate_units(input: &str) -> Result<(String, String), String> {
+    let term = parse_term(input, &UNIT_CONSTANTS).map_err(|err| err.to_string())?;
+    let rates = match RATES.as_ref() {
+        Ok(rates) if !rates.currency_codes(&term).is_empty() => rates,
+        // Without rates only codes in capitals are taken for currencies.
+        Err(err)
+            if input
+                .split(|c: char| !c.is_alphabetic())
+                .any(is_currency_code) =>
+        {
+            return Err(err.clone());
+        }
+        _ => {
+            let result = evaluate(&term).map_err(|err| err.to_string())?;
+            return Ok((term.to_string(), result));
+        }
+    };
+    let result = currency_result(&term, rates, today()).map_err(|err| err.to_string())?;
+    let (value, unit) = result.unwrap_or_default();
+    Ok((
+        term.to_string(),
+        format!("{value} {unit}").trim_end().to_owned(),
+    ))
+}
+
+/// ASCII diagram of the parsed input, empty while it doesn't parse.
+fn diagram<T: Numeric>(input: &str, constants: &Constants<T>) -> String {
+    parse_term(input, constants)
+        .map(|term| to_ascii(&term))
+        .unwrap_or_default()
+}
+
 impl Calculator {
+    /// Literals like `1.5±0.01` or `[1, 2]` switch to interval arithmetic, or to error
+    /// propagation in the `σ` mode. Dates, times and durations like `17:45` or `90 days`
+    /// switch to time arithmetic, unit symbols like `km`, currency codes like `EUR` and `to`
+    /// to quantities.
+    fn arithmetic(&self) -> Arithmetic {
+        let is_word =
+            |word: &str| matches!(word, "to" | "in") || is_unit(word) || is_currency_code(word);
+        let is_duration = |word: &str| is_duration_word(word) && !is_unit(word);
+        let mut words = self.input.split(|c: char| !c.is_alphabetic());
+        match () {
+            _ if self.input.contains(['±', '[']) && self.gaussian => Arithmetic::Gaussian,
+            _ if self.input.contains(['±', '[']) => Arithmetic::Interval,
+            _ if words.clone().any(is_duration) => Arithmetic::Time,
+            // `x > 0 ? 10:20` is a conditional, not a time that leaves the `?` incomplete.
+            _ if has_literal(&self.input)
+                && !matches!(
+                    parse_term(&self.input, &TEMPORAL_CONSTANTS),
+                    Err(ParsingError::IncompleteConditional)
+                ) =>
+            {
+                Arithmetic::Time
+            }
+            _ if words.any(is_word) => Arithmetic::Units,
+            _ => Arithmetic::Real,
+        }
+    }
+
+    /// Pushes the pending input onto the RPN stack followed by `word`.
+    fn enter_rpn(&mut self, word: &str) {
+        let words = format!("{} {}", self.input, word);
+        match self.rpn_stack.enter(&words, &CONSTANTS) {
+            Ok(()) => {
+                self.error = None;
+                self.input = "".to_owned();
+            }
+            Err(err) => self.error = Some(format!("Something went wrong: {}", err)),
+        }
+    }
+
     pub fn update(&mut self, message: Message) {
         match message {
             Message::InputChanged(input) => {
                 self.input = input;
             }
+            // Enter without input duplicates the top of the stack like on HP calculators.
+            Message::Calculate if self.rpn && self.input.trim().is_empty() => self.enter_rpn("dup"),
+            Message::Calculate if self.rpn => self.enter_rpn(""),
             Message::Calculate => {
-                let term = parse_term(&self.input, &CONSTANTS);
-                match term {
-                    Ok(term) => {
+                let input = &self.input;
+                let calculated = match self.arithmetic() {
+                    Arithmetic::Real => calculate_input(input, &CONSTANTS),
+                    Arithmetic::Interval => calculate_input(input, &INTERVAL_CONSTANTS),
+                    Arithmetic::Gaussian => calculate_input(input, &UNCERTAIN_CONSTANTS),
+                    Arithmetic::Units => calculate_units(input),
+                    Arithmetic::Time => calculate_input(input, &TEMPORAL_CONSTANTS),
+                };
+                match calculated {
+                    Ok((term, result)) => {
                         self.error = None;
-                        let result: f32 = calculate_result(&term);
-                        self.stack.push(self.input.clone());
-                        self.input = result.to_string();
+                        self.stack.push(term);
+                        self.input = result;
                     }
                     Err(err) => self.error = Some(format!("Something went wrong: {}", err)),
                 };
             }
+            Message::KeypadPressed(pad)
+                if self.rpn && !pad.contains(|c: char| c.is_ascii_digit() || c == '.') =>
+            {
+                self.enter_rpn(pad.trim());
+            }
             Message::KeypadPressed(pad) => {
-                self.input = self.input.clone() + &pad;
+                self.input = self.input.clone() + pad;
             }
             Message::RemoveLast => {
                 let mut modified = self.input.trim_end().to_string();
                 modified.pop();
                 self.input = modified;
             }
+            Message::Clear if self.rpn && self.input.is_empty() => self.rpn_stack.clear(),
             Message::Clear => {
                 self.input = "".to_owned();
             }
+            Message::ToggleRpn => {
+                self.rpn = !self.rpn;
+                self.error = None;
+            }
+            Message::ToggleGaussian => {
+                self.gaussian = !self.gaussian;
+                self.error = None;
+            }
+            Message::Rpn(word) => self.enter_rpn(word),
         }
     }
 
@@ -80,12 +253,27 @@ impl Calculator {
     pub fn view(&self) -> Column<'_, Message> {
         let spacing = 4;
 
+        let stack_key = |label: &'static str| {
+            keypad(label).on_press_maybe(self.rpn.then_some(Message::Rpn(label)))
+        };
+
         let keypad = column![
+            row![
+                keypad(if self.rpn { "ALG" } else { "RPN" }).on_press(Message::ToggleRpn),
+                stack_key("swap"),
+                stack_key("dup"),
+                match self.rpn {
+                    true => stack_key("drop"),
+                    false => keypad(if self.gaussian { "[ ]" } else { "σ" })
+                        .on_press(Message::ToggleGaussian),
+                },
+            ]
+            .spacing(spacing),
             row![
                 keypad("B").on_press(Message::RemoveLast),
                 keypad("C").on_press(Message::Clear),
-                keypad("%"),
-                keypad("/"),
+                keypad("mod").on_press(Message::KeypadPressed(" mod ")),
+                keypad("/").on_press(Message::KeypadPressed(" / ")),
             ]
             .spacing(spacing),
             row![
@@ -110,10 +298,13 @@ impl Calculator {
             ]
             .spacing(spacing),
             row![
-                keypad(" ").on_press(Message::KeypadPressed(" ")),
+                match self.rpn {
+                    true => stack_key("roll"),
+                    false => keypad(" ").on_press(Message::KeypadPressed(" ")),
+                },
                 keypad("0").on_press(Message::KeypadPressed("0")),
-                keypad(",").on_press(Message::KeypadPressed(",")),
-                keypad("=").on_press(Message::Calculate),
+                keypad(".").on_press(Message::KeypadPressed(".")),
+                keypad(if self.rpn { "Enter" } else { "=" }).on_press(Message::Calculate),
             ]
             .spacing(spacing),
         ]
@@ -130,8 +321,16 @@ impl Calculator {
             .into();
 
         let stack_or_error = match &self.error {
+            // Stack levels numbered from the top, the last four are shown.
+            None if self.rpn => {
+                let values = self.rpn_stack.values();
+                let levels = (1..=values.len().min(4))
+                    .rev()
+                    .map(|level| format!("{}: {}", level, values[values.len() - level]));
+                text(levels.collect::<Vec<_>>().join("\n"))
+            }
             None => {
-                if self.stack.len() == 0 {
+                if self.stack.is_empty() {
                     text("")
                 } else {
                     let start = (self.stack.len() as i64 - 3).max(0) as usize;
@@ -150,14 +349,59 @@ impl Calculator {
         .align_x(Alignment::End)
         .size(12);
 
-        column![text_in, container(stack_or_error).padding([4, 5]), keypad,].padding([10, 5])
+        // Diagram of the term being typed, empty while it doesn't parse.
+        let input = &self.input;
+        let diagram = match self.arithmetic() {
+            _ if self.rpn => parse_rpn(input, &CONSTANTS)
+                .map(|term| to_ascii(&term))
+                .unwrap_or_default(),
+            Arithmetic::Real => diagram(input, &CONSTANTS),
+            Arithmetic::Interval => diagram(input, &INTERVAL_CONSTANTS),
+            Arithmetic::Gaussian => diagram(input, &UNCERTAIN_CONSTANTS),
+            Arithmetic::Units => diagram(input, &UNIT_CONSTANTS),
+            Arithmetic::Time => diagram(input, &TEMPORAL_CONSTANTS),
+        };
+        let tree_panel = scrollable(text(diagram).font(Font::MONOSPACE).size(12))
+            .width(full_width)
+            .height(150);
+
+        column![
+            text_in,
+            container(stack_or_error).padding([4, 5]),
+            keypad,
+            container(tree_panel).padding([8, 0]),
+        ]
+        .padding([10, 5])
+    }
+}
+
+/// Prints the tree of the term following `--dot` or `--ascii` instead of opening the window.
+/// Returns false if no such flag was given.
+fn render_from_args(args: &[String]) -> bool {
+    let render: fn(&Tree<Token<f32>>) -> String = match args.first().map(String::as_str) {
+        Some("--dot") => to_dot,
+        Some("--ascii") => to_ascii,
+        _ => return false,
+    };
+    match parse_term(&args[1..].join(" "), &CONSTANTS) {
+        Ok(term) => print!("{}", render(&term)),
+        Err(err) => {
+            eprintln!("Something went wrong: {}", err);
+            std::process::exit(1);
+        }
     }
+    true
 }
 
 fn main() -> iced::Result {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if render_from_args(&args) {
+        return Ok(());
+    }
+
     let app = application("Calculator", Calculator::update, Calculator::view).window_size(Size {
         width: Calculator::width() + 10.0,
-        height: 360.0,
+        height: 640.0,
     });
     app.run()
 }
@@ -170,9 +414,9 @@ mod tests {
     #[test]
     fn test_basic_operations() {
         let term_tree = parse_term("(3 + 4) * (17 + 3 * -4 + 5) =", &CONSTANTS).unwrap();
-        term_tree.traverse_postorder(0, &mut |node| println!("{node:?}"));
+        term_tree.traverse_postorder(term_tree.root().unwrap(), &mut |node| println!("{node:?}"));
         println!("{:#?}", term_tree);
-        let result: f32 = calculate_result(&term_tree);
+        let result: f32 = calculate_result(&term_tree).unwrap();
         println!("{result:?}");
 
         assert_eq!(result as i32, (3 + 4) * (17 + 3 * -4 + 5));
@@ -182,9 +426,121 @@ mod tests {
     fn test_minus() {
         let second_term = parse_term("3 - 4", &CONSTANTS).unwrap();
 
-        let result: f32 = calculate_result(&second_term);
+        let result: f32 = calculate_result(&second_term).unwrap();
         println!("{result:?}");
 
         assert_eq!(result as i32, 3 - 4);
     }
+
+    #[test]
+    fn test_symbolic_result() {
+        let term = parse_term("diff(x^3 + 2x, x)", &CONSTANTS).unwrap();
+        assert_eq!(Ok("3 * x^2 + 2".to_owned()), evaluate(&term));
+        let term = parse_term("expand((x + 1)^3)", &CONSTANTS).unwrap();
+        assert_eq!(Ok("x^3 + 3 * x^2 + 3 * x + 1".to_owned()), evaluate(&term));
+        let term = parse_term("factor(x^2 - 1)", &CONSTANTS).unwrap();
+        assert_eq!(Ok("(x - 1) * (x + 1)".to_owned()), evaluate(&term));
+    }
+
+    #[test]
+    fn test_equations() {
+        let solve = |s: &str| evaluate(&parse_term(s, &CONSTANTS).unwrap());
+        assert_eq!(Ok("x = 4".to_owned()), solve("2x + 3 = 11"));
+        assert_eq!(Ok("y = -3 or y = 1".to_owned()), solve("y^2 + 2y = 3"));
+        assert_eq!(Ok("no solution for x".to_owned()), solve("x^2 = -1"));
+        assert_eq!(Ok("a = 0.5".to_owned()), solve("solve(2a = 1, a)"));
+    }
+
+    #[test]
+    fn test_interval_input() {
+        let mut calculator = Calculator::default();
+        calculator.update(Message::InputChanged("[1, 2] * (3±0.5)".to_owned()));
+        calculator.update(Message::Calculate);
+        assert_eq!(None, calculator.error);
+        assert_eq!("[2.5, 7]", calculator.input);
+
+        calculator.update(Message::InputChanged("1 / [-1, 1]".to_owned()));
+        calculator.update(Message::Calculate);
+        assert!(calculator.error.is_some());
+    }
+
+    #[test]
+    fn test_gaussian_input() {
+        let mut calculator = Calculator::default();
+        calculator.update(Message::ToggleGaussian);
+        calculator.update(Message::InputChanged("(3±0.3) + (4±0.4)".to_owned()));
+        calculator.update(Message::Calculate);
+        assert_eq!(None, calculator.error);
+        assert_eq!("7±0.5", calculator.input);
+
+        calculator.update(Message::InputChanged("[1, 2]".to_owned()));
+        calculator.update(Message::Calculate);
+        assert!(calculator.error.is_some());
+    }
+
+    #[test]
+    fn test_units_input() {
+        let mut calculator = Calculator::default();
+        for (input, result) in [
+            ("3 m + 20 cm", "3.2 m"),
+            ("9.81 m/s^2 * 2 kg", "19.62 N"),
+            ("60 mph to km/h", "96.56063 km / h"),
+        ] {
+            calculator.update(Message::InputChanged(input.to_owned()));
+            calculator.update(Message::Calculate);
+            assert_eq!(None, calculator.error);
+            assert_eq!(result, calculator.input);
+        }
+
+        calculator.update(Message::InputChanged("3 m + 2 s".to_owned()));
+        calculator.update(Message::Calculate);
+        let error = calculator.error.unwrap();
+        assert!(
+            error.contains("3 m and 2 s have different units"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_time_input() {
+        let mut calculator = Calculator::default();
+        for (input, result) in [
+            ("2026-10-18 + 90 days", "2027-01-16"),
+            ("17:45 - 09:30", "PT8H15M"),
+            ("PT8H15M to h", "8.25 h"),
+            ("09:30 + 2 weeks", "09:30"),
+            ("1 > 0 ? 10:20 : 11:00", "10:20"),
+            ("1 > 0 ? 10:20", "10"),
+        ] {
+            calculator.update(Message::InputChanged(input.to_owned()));
+            calculator.update(Message::Calculate);
+            assert_eq!(None, calculator.error);
+            assert_eq!(result, calculator.input);
+        }
+
+        for input in ["2026-13-01", "2026-02-30"] {
+            calculator.update(Message::InputChanged(input.to_owned()));
+            calculator.update(Message::Calculate);
+            assert!(calculator.error.is_some(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_rpn_mode() {
+        let mut calculator = Calculator::default();
+        calculator.update(Message::ToggleRpn);
+        for message in [
+            Message::InputChanged("3".to_owned()),
+            Message::Calculate,
+            Message::KeypadPressed("4"),
+            Message::KeypadPressed(" + "),
+            Message::Calculate,
+            Message::KeypadPressed(" * "),
+        ] {
+            calculator.update(message);
+        }
+
+        assert_eq!(None, calculator.error);
+        assert_eq!(&[49.0], calculator.rpn_stack.values());
+    }
 }
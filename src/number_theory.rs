@@ -0,0 +1,195 @@
+//! Exact integer algorithms and the gamma function backing the
+//! combinatorics and number theory built-ins.
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Deterministic Miller-Rabin, the witnesses below cover the whole u64 range.
+pub fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    'witness: for a in WITNESSES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Pollard's rho with Floyd cycle detection, returns a non-trivial divisor of
+/// the composite number `n`.
+fn pollard_rho(n: u64) -> u64 {
+    if n.is_multiple_of(2) {
+        return 2;
+    }
+    for c in 1.. {
+        let f = |x: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+/// Prime factors of `n` in ascending order, repeated according to their multiplicity.
+/// Zero and one have no prime factors.
+pub fn prime_factors(n: u64) -> Vec<u64> {
+    let mut factors = vec![];
+    let mut stack = vec![n];
+    while let Some(m) = stack.pop() {
+        if m < 2 {
+            continue;
+        }
+        if is_prime(m) {
+            factors.push(m);
+            continue;
+        }
+        let d = pollard_rho(m);
+        stack.push(d);
+        stack.push(m / d);
+    }
+    factors.sort_unstable();
+    factors
+}
+
+pub fn factorial(n: u64) -> Option<u64> {
+    (2..=n).try_fold(1u64, |acc, i| acc.checked_mul(i))
+}
+
+/// Number of ordered selections of `k` out of `n`, `n! / (n - k)!`.
+pub fn permutations(n: u64, k: u64) -> Option<u64> {
+    if k > n {
+        return Some(0);
+    }
+    (n - k + 1..=n).try_fold(1u64, |acc, i| acc.checked_mul(i))
+}
+
+/// Number of unordered selections of `k` out of `n`, `n! / (k! (n - k)!)`.
+pub fn combinations(n: u64, k: u64) -> Option<u64> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        // Stays exact, result is always a binomial coefficient after the division.
+        result = result * (n - i) as u128 / (i + 1) as u128;
+        if result > u64::MAX as u128 {
+            return None;
+        }
+    }
+    Some(result as u64)
+}
+
+/// Gamma function using the Lanczos approximation (g = 7, n = 9),
+/// accurate to about 15 significant digits.
+pub fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        return std::f64::consts::PI / ((std::f64::consts::PI * x).sin() * gamma(1.0 - x));
+    }
+
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    (2.0 * std::f64::consts::PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primes() {
+        assert!(is_prime(2));
+        assert!(is_prime(1_000_000_007));
+        assert!(is_prime(18_446_744_073_709_551_557));
+        assert!(!is_prime(1));
+        assert!(!is_prime(3_215_031_751));
+
+        assert_eq!(vec![2, 2, 2, 3, 3, 5], prime_factors(360));
+        assert_eq!(
+            vec![1_000_000_007, 1_000_000_009],
+            prime_factors(1_000_000_016_000_000_063)
+        );
+        assert!(prime_factors(1).is_empty());
+    }
+
+    #[test]
+    fn test_combinatorics() {
+        assert_eq!(Some(120), factorial(5));
+        assert_eq!(None, factorial(21));
+        assert_eq!(Some(60), permutations(5, 3));
+        assert_eq!(Some(10), combinations(5, 2));
+        assert_eq!(Some(0), combinations(2, 5));
+        assert_eq!(Some(53_194_089_192_720), combinations(60, 15));
+    }
+
+    #[test]
+    fn test_gamma() {
+        assert!((gamma(5.0) - 24.0).abs() < 1e-10);
+        assert!((gamma(0.5) - std::f64::consts::PI.sqrt()).abs() < 1e-12);
+    }
+}
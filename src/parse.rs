@@ -1,12 +1,13 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::LazyLock};
 
 use regex::Regex;
 use thiserror::Error;
 
 use crate::{
+    datetime::leading_literal,
     token::{Operator, Token},
     traits::{Constants, Numeric},
-    tree::BinaryTree,
+    tree::{NodeId, Tree},
 };
 
 #[derive(Error, Debug)]
@@ -15,28 +16,52 @@ pub enum ParsingError {
     EmptyTree,
     #[error("Input {0} is not allowed")]
     InvalidInput(String),
+    #[error("Parentheses are not balanced")]
+    UnbalancedParentheses,
+    #[error("Operator {0} is missing an operand")]
+    MissingOperand(String),
+    #[error("Function {0} has to be followed by its arguments in parentheses")]
+    MissingArguments(String),
+    #[error("Function {0} takes {1} arguments but got {2}")]
+    WrongArgumentCount(String, usize, usize),
+    #[error("Conditional is missing its : branch")]
+    IncompleteConditional,
+    #[error("{0} values are left on the stack, expected a single one")]
+    LeftoverOperands(usize),
 }
 
+static TOKEN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)(0[bB][01]*(?:[iu]\d{1,2})?)|(0[xX][0-9A-Fa-f]*(?:[iu]\d{1,2})?)|(?:\[\s*-?\d+(?:\.\d+)?(?:[eE][+-]?\d+)?\s*,\s*-?\d+(?:\.\d+)?(?:[eE][+-]?\d+)?\s*\])|(?:\d+(?:\.\d+)?(?:[eE][+-]?\d+)?(?:\s*±\s*\d+(?:\.\d+)?(?:[eE][+-]?\d+)?)?)|//|==|!=|<=|>=|&&|\|\||[()+*/=!,<>?:^-]|([A-Za-zµ]+)")
+        .unwrap()
+});
+
 fn tokenize_term<T: Numeric>(
     term: &str,
     constants: &Constants<T>,
 ) -> Result<Vec<Token<T>>, ParsingError> {
-    let regex =
-        Regex::new(r"(?m)(0[bB][01]*(?:[iu]\d{1,2})?)|(0[xX][0-9A-Fa-f]*(?:[iu]\d{1,2})?)|(?:-?\d+(?:\.|,\d+)?)|[()+*-=]|([A-Za-z]+)")
-            .unwrap();
-    let term_lower = term.to_lowercase();
-    let result = regex.find_iter(&term_lower);
-
     let mut tokens: Vec<Token<T>> = vec![];
 
-    for m in result {
-        println!("{:#?}", m);
-        let token = match m.as_str() {
+    let mut start = 0;
+    while let Some(m) = TOKEN_REGEX.find_at(term, start) {
+        // Dates, times and durations are values for number types that read them, for the
+        // others `2000-12-31` is a difference and `10:20` the branches of a `? :`.
+        let literal = leading_literal(&term[m.start()..])
+            .and_then(|literal| Some((literal, T::from_temporal_str(literal)?)));
+        if let Some((literal, value)) = literal {
+            let value = value.map_err(|_| ParsingError::InvalidInput(literal.to_owned()))?;
+            tokens.push(Token::Value(value));
+            start = m.start() + literal.len();
+            continue;
+        }
+        start = m.end();
+        let token = match m.as_str().to_lowercase().as_str() {
             "e" => Token::Value(constants.e),
             "pi" => Token::Value(constants.pi),
-            other => match Token::<T>::from_str(other) {
-                Ok(t) => t,
-                Err(err) => {
+            other => match (Token::<T>::from_str(other), m.as_str().parse::<T>()) {
+                // Values like units are case sensitive, `Mm` is a megametre and `mm` a millimetre.
+                (Ok(Token::Var(_) | Token::Value(_)), Ok(value)) => Token::Value(value),
+                (Ok(t), _) => t,
+                (Err(err), _) => {
                     println!("Token not allowed: {}, {err:?}", other);
                     return Err(ParsingError::InvalidInput(other.to_owned()));
                 }
@@ -45,166 +70,482 @@ fn tokenize_term<T: Numeric>(
         tokens.push(token);
     }
 
-    return Ok(tokens);
+    Ok(tokens)
+}
+
+/// Token of a single lowercase word of an RPN term or S-expression, where grouping
+/// and separators have no meaning. `neg` and `not` stand for the prefix `-` and `!`.
+pub(crate) fn word_token<T: Numeric>(
+    word: &str,
+    constants: &Constants<T>,
+) -> Result<Token<T>, ParsingError> {
+    let token = match word {
+        "e" => Token::Value(constants.e),
+        "pi" => Token::Value(constants.pi),
+        "neg" => Token::Op(Operator::NEG),
+        "not" => Token::Op(Operator::NOT),
+        other => {
+            Token::from_str(other).map_err(|_| ParsingError::InvalidInput(other.to_owned()))?
+        }
+    };
+    match token {
+        Token::Value(_) | Token::Var(_) => Ok(token),
+        Token::Op(_) | Token::Func(_) if token.arity() > 0 => Ok(token),
+        _ => Err(ParsingError::InvalidInput(word.to_owned())),
+    }
 }
 
-pub fn validate_term<T: Numeric>(tokens: &Vec<Token<T>>) -> bool {
-    let mut in_count = 0;
-    let mut out_count = 0;
+pub fn validate_term<T: Numeric>(tokens: &[Token<T>]) -> bool {
+    let mut depth: i32 = 0;
     for t in tokens.iter() {
         if t.is_in() {
-            in_count += 1;
+            depth += 1;
         }
         if t.is_out() {
-            out_count += 1;
+            depth -= 1;
+        }
+        if depth < 0 {
+            return false;
         }
     }
 
-    in_count == out_count
+    depth == 0
 }
 
 pub fn rectify_term<T: Numeric>(tokens: &mut Vec<Token<T>>) {
-    println!("{tokens:#?}");
-    let mut tokens_len = tokens.len();
-    let mut idx = 0;
-    while idx < tokens_len {
-        let t = tokens[idx];
-        if t == Operator::SUB {
-            tokens[idx] = Token::add();
-
-            tokens.push(Token::Empty);
-            tokens.push(Token::Empty);
-
-            for i in idx + 1..tokens_len {
-                tokens[i + 2] = tokens[i];
-            }
-
-            tokens[idx + 1] = Token::Value(T::one().neg());
-            tokens[idx + 2] = Token::mul();
-            tokens_len += 2;
-            idx += 2;
-        }
-        idx += 1;
-    }
     if tokens.last().is_none_or(|t| !t.is_eq()) {
         tokens.push(Token::eq());
     }
 }
 
-pub fn parse_term<T: Numeric>(
-    term: &str,
-    constants: &Constants<T>,
-) -> Result<BinaryTree<Token<T>>, ParsingError> {
-    let mut tokens: Vec<Token<T>> = tokenize_term(term, constants)?;
-    rectify_term(&mut tokens);
-
-    let valid = validate_term(&tokens);
-    if !valid {
-        panic!("Invalid term");
-    } else {
-        println!("Valid term");
+/// Inserts the `*` implied by juxtaposed operands like `2pi`, `3(4 + 5)` or `(a + b)(a - b)`.
+/// The inserted operator is an ordinary `*`, so `1 / 2pi` is `(1 / 2) * pi`.
+pub fn insert_implicit_multiplication<T: Numeric>(tokens: &mut Vec<Token<T>>) {
+    let mut result = Vec::with_capacity(tokens.len());
+    // True if the previous token completes an operand.
+    let mut ends_operand = false;
+    for t in tokens.drain(..) {
+        let starts_operand = matches!(
+            t,
+            Token::Value(_) | Token::Var(_) | Token::Func(_) | Token::Op(Operator::IN)
+        );
+        if ends_operand && starts_operand {
+            result.push(Token::mul());
+        }
+        ends_operand = match t {
+            Token::Value(_) | Token::Var(_) | Token::Op(Operator::OUT) => true,
+            // A `!` after an operand is a factorial, before one it is a logical not.
+            Token::Op(Operator::FAC) => ends_operand,
+            _ => false,
+        };
+        result.push(t);
     }
+    *tokens = result;
+}
 
-    // TODO: Append missing =
-    // TODO: Implement for multi digit inputs -> Parse tokens with regex and use Strings instead of chars.
-    // Idea: Use custom operator struct: struct { operator: Option<OperatorEnum>, value: i32 }
-
-    let mut s1: Vec<Token<T>> = Vec::new();
-    let mut s2: Vec<BinaryTree<Token<T>>> = Vec::new();
-
-    println!("{tokens:#?}");
-
-    let mut idx = 0;
-    while idx < tokens.len() {
-        let t = tokens[idx]; // Safe to call, as there is always at least on &str in the string
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Read juxtaposed operands as a multiplication, see [`insert_implicit_multiplication`].
+    pub implicit_multiplication: bool,
+}
 
-        if t.is_in() || t.is_mul() {
-            s1.push(t);
-            idx += 1;
-            continue;
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            implicit_multiplication: true,
         }
+    }
+}
 
-        if t.is_add() && s1.last().is_some_and(|x| x.is_mul()) && s2.len() >= 2 {
+/// Pops `arity` subtrees from `s2` and pushes them back joined under `t`.
+pub(crate) fn reduce<T: Numeric>(
+    t: Token<T>,
+    arity: usize,
+    s2: &mut Vec<NodeId>,
+    tree: &mut Tree<Token<T>>,
+) -> Result<(), ParsingError> {
+    if arity == 0 {
+        return Err(ParsingError::InvalidInput(t.to_string()));
+    }
+    if s2.len() < arity {
+        return Err(ParsingError::MissingOperand(t.to_string()));
+    }
+    let children = s2.split_off(s2.len() - arity);
+    s2.push(tree.push(t, children));
+    Ok(())
+}
+
+/// Reduces the topmost pending token. A `:` completes the `?` right below it
+/// into a single conditional node with the condition and both branches.
+fn reduce_top<T: Numeric>(
+    s1: &mut Vec<Token<T>>,
+    s2: &mut Vec<NodeId>,
+    tree: &mut Tree<Token<T>>,
+) -> Result<(), ParsingError> {
+    match s1.pop() {
+        Some(Token::Op(Operator::ELSE)) => {
             s1.pop();
-            let l = s2.len();
-            let y = s2.pop().unwrap();
-            let x = s2.get_mut(l - 2).unwrap();
-            x.bin(Token::mul(), y);
-            continue;
+            reduce(Token::Op(Operator::COND), 3, s2, tree)
         }
-
-        if t.is_add() {
-            s1.push(t);
-            idx += 1;
-            continue;
+        Some(Token::Op(Operator::COND)) => Err(ParsingError::IncompleteConditional),
+        Some(t) => {
+            let arity = t.arity();
+            reduce(t, arity, s2, tree)
         }
+        None => Ok(()),
+    }
+}
 
-        if t.is_out() && s1.last().is_some_and(|x| x.is_add()) && s2.len() >= 2 {
-            s1.pop();
-            let l = s2.len();
-            let y = s2.pop().unwrap();
-            let x = s2.get_mut(l - 2).unwrap();
-            x.bin(Token::add(), y);
-            continue;
-        }
+/// Reduces pending operators until the innermost open parenthesis.
+fn reduce_group<T: Numeric>(
+    s1: &mut Vec<Token<T>>,
+    s2: &mut Vec<NodeId>,
+    tree: &mut Tree<Token<T>>,
+) -> Result<(), ParsingError> {
+    while s1.last().is_some_and(|t| !t.is_in()) {
+        reduce_top(s1, s2, tree)?;
+    }
+    Ok(())
+}
 
-        if t.is_out() && s1.last().is_some_and(|x| x.is_mul()) && s2.len() >= 2 {
-            s1.pop();
-            let l = s2.len();
-            let y = s2.pop().unwrap();
-            let x = s2.get_mut(l - 2).unwrap();
-            x.bin(Token::mul(), y);
-            continue;
+/// Reduces everything above the `?` that a `:` belongs to. Nested conditionals
+/// that are already complete get reduced on the way.
+fn reduce_condition<T: Numeric>(
+    s1: &mut Vec<Token<T>>,
+    s2: &mut Vec<NodeId>,
+    tree: &mut Tree<Token<T>>,
+) -> Result<(), ParsingError> {
+    loop {
+        match s1.last() {
+            Some(Token::Op(Operator::COND)) => return Ok(()),
+            Some(Token::Op(Operator::IN)) | None => {
+                return Err(ParsingError::InvalidInput(Operator::ELSE.to_string()));
+            }
+            Some(_) => reduce_top(s1, s2, tree)?,
         }
+    }
+}
 
-        if t.is_out() && s1.last().is_some_and(|x| x.is_in()) {
-            s1.pop();
-            idx += 1;
-            continue;
-        }
+pub fn parse_term<T: Numeric>(
+    term: &str,
+    constants: &Constants<T>,
+) -> Result<Tree<Token<T>>, ParsingError> {
+    parse_term_with_options(term, constants, &ParseOptions::default())
+}
 
-        if t.is_eq() && s1.last().is_some_and(|x| x.is_add()) && s2.len() >= 2 {
-            s1.pop();
-            let l = s2.len();
-            let y = s2.pop().unwrap();
-            let x = s2.get_mut(l - 2).unwrap();
-            x.bin(Token::add(), y);
-            continue;
-        }
+pub fn parse_term_with_options<T: Numeric>(
+    term: &str,
+    constants: &Constants<T>,
+    options: &ParseOptions,
+) -> Result<Tree<Token<T>>, ParsingError> {
+    let mut tokens: Vec<Token<T>> = tokenize_term(term, constants)?;
+    rectify_term(&mut tokens);
+    if options.implicit_multiplication {
+        insert_implicit_multiplication(&mut tokens);
+    }
 
-        if t.is_eq() && s1.last().is_some_and(|x| x.is_mul()) && s2.len() >= 2 {
-            s1.pop();
-            let l = s2.len();
-            let y = s2.pop().unwrap();
-            let x = s2.get_mut(l - 2).unwrap();
-            x.bin(Token::mul(), y);
-            continue;
-        }
+    if !validate_term(&tokens) {
+        return Err(ParsingError::UnbalancedParentheses);
+    }
 
-        if t.is_eq() && s1.is_empty() && s2.len() == 1 {
-            return s2.last().ok_or(ParsingError::EmptyTree).cloned();
+    // s1 holds pending operators and functions, s2 the roots of the operand subtrees
+    // built so far. All subtrees share the nodes of `tree`.
+    let mut tree: Tree<Token<T>> = Tree::new();
+    let mut s1: Vec<Token<T>> = Vec::new();
+    let mut s2: Vec<NodeId> = Vec::new();
+    // Number of arguments seen inside each open parenthesis.
+    let mut args: Vec<usize> = Vec::new();
+    // True whenever the next token has to start an operand, which makes a `-` unary.
+    let mut expect_operand = true;
+
+    for (idx, t) in tokens.iter().enumerate() {
+        match t {
+            Token::Value(_) | Token::Var(_) => {
+                s2.push(tree.push(t.clone(), vec![]));
+                expect_operand = false;
+            }
+            Token::Func(_) => {
+                if !tokens.get(idx + 1).is_some_and(|next| next.is_in()) {
+                    return Err(ParsingError::MissingArguments(t.to_string()));
+                }
+                s1.push(t.clone());
+            }
+            Token::Op(Operator::IN) => {
+                s1.push(t.clone());
+                args.push(1);
+                expect_operand = true;
+            }
+            Token::Op(Operator::SEP) => {
+                reduce_group(&mut s1, &mut s2, &mut tree)?;
+                match args.last_mut() {
+                    Some(count) => *count += 1,
+                    None => return Err(ParsingError::InvalidInput(t.to_string())),
+                }
+                expect_operand = true;
+            }
+            Token::Op(Operator::OUT) => {
+                reduce_group(&mut s1, &mut s2, &mut tree)?;
+                s1.pop();
+                let count = args.pop().unwrap_or(1);
+                match s1.last() {
+                    Some(&Token::Func(func)) => {
+                        s1.pop();
+                        if count != func.arity() {
+                            return Err(ParsingError::WrongArgumentCount(
+                                func.to_string(),
+                                func.arity(),
+                                count,
+                            ));
+                        }
+                        reduce(Token::Func(func), count, &mut s2, &mut tree)?;
+                    }
+                    _ if count > 1 => return Err(ParsingError::InvalidInput(",".to_owned())),
+                    _ => {}
+                }
+                expect_operand = false;
+            }
+            // The `=` that ends the input.
+            Token::Op(Operator::EQ) if idx + 1 == tokens.len() => break,
+            Token::Op(Operator::EQ) => {
+                // Both sides of an equation are complete terms, and they can't be equations again.
+                if s1
+                    .iter()
+                    .rev()
+                    .take_while(|t| !t.is_in())
+                    .any(|t| t.is_eq())
+                {
+                    return Err(ParsingError::InvalidInput(t.to_string()));
+                }
+                reduce_group(&mut s1, &mut s2, &mut tree)?;
+                s1.push(t.clone());
+                expect_operand = true;
+            }
+            // Prefix operators wait for their operand, so they never reduce anything.
+            Token::Op(Operator::SUB) if expect_operand => s1.push(Token::Op(Operator::NEG)),
+            Token::Op(Operator::FAC) if expect_operand => s1.push(Token::Op(Operator::NOT)),
+            Token::Op(Operator::FAC) => {
+                // Postfix operators bind tightest and apply to the operand right before them.
+                reduce(t.clone(), 1, &mut s2, &mut tree)?;
+            }
+            Token::Op(Operator::ELSE) => {
+                reduce_condition(&mut s1, &mut s2, &mut tree)?;
+                s1.push(t.clone());
+                expect_operand = true;
+            }
+            Token::Op(op) => {
+                while let Some(&Token::Op(top)) = s1.last() {
+                    let binds_tighter = top.precedence() > op.precedence()
+                        || (top.precedence() == op.precedence() && op.is_left_associative());
+                    if !binds_tighter {
+                        break;
+                    }
+                    reduce_top(&mut s1, &mut s2, &mut tree)?;
+                }
+                s1.push(t.clone());
+                expect_operand = true;
+            }
+            Token::Empty => {}
         }
+    }
 
-        idx += 1;
-        s2.push(BinaryTree::new_with_root(t, Token::Empty));
+    while !s1.is_empty() {
+        reduce_top(&mut s1, &mut s2, &mut tree)?;
+    }
+
+    match s2.as_slice() {
+        &[root] => {
+            tree.set_root(root);
+            Ok(tree)
+        }
+        _ => Err(ParsingError::EmptyTree),
     }
-    Err(ParsingError::EmptyTree)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{calculate::calculate_result, token::Function};
+    use std::f32::consts::{E, PI};
+
+    /// Node reached from the root by following the given child positions.
+    fn at<'a, T>(tree: &'a Tree<T>, path: &[usize]) -> Option<&'a T> {
+        let mut id = tree.root()?;
+        for &n in path {
+            id = tree.child(id, n)?;
+        }
+        tree.get(id)
+    }
 
     #[test]
     fn test_tokenize_term() {
         let s = "0xffi16 + 1";
-        let exp = vec![Token::Value(255.0), Token::add(), Token::Value(1.0)];
+        let exp = [Token::Value(255.0), Token::add(), Token::Value(1.0)];
 
-        let constants = Constants { e: 2.7, pi: 3.14 };
+        let constants = Constants { e: E, pi: PI };
 
         let tokens = tokenize_term(s, &constants).unwrap();
         println!("{:#?}", tokens);
 
         assert!(exp[0] == tokens[0]);
     }
+
+    #[test]
+    fn test_tokenize_scientific() {
+        let constants = Constants { e: E, pi: PI };
+
+        let tokens = tokenize_term::<f64>("6.022e23 * 1.5E-3", &Constants { e: 2.7, pi: 3.0 });
+        assert_eq!(
+            vec![Token::Value(6.022e23), Token::mul(), Token::Value(1.5e-3)],
+            tokens.unwrap()
+        );
+
+        let tokens = tokenize_term::<f32>("2e + 1e3e", &constants).unwrap();
+        let exp = vec![
+            Token::Value(2.0),
+            Token::Value(E),
+            Token::add(),
+            Token::Value(1000.0),
+            Token::Value(E),
+        ];
+        assert_eq!(exp, tokens);
+
+        let tokens = tokenize_term::<i64>("12e-1", &Constants { e: 2, pi: 3 });
+        assert!(matches!(tokens, Err(ParsingError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_tokenize_division_operators() {
+        let constants = Constants { e: E, pi: PI };
+        let tokens = tokenize_term::<f32>("7 // 2 mod 3 rem 4 / 1.5", &constants).unwrap();
+        let exp = vec![
+            Token::Value(7.0),
+            Token::Op(Operator::IDIV),
+            Token::Value(2.0),
+            Token::Op(Operator::MOD),
+            Token::Value(3.0),
+            Token::Op(Operator::REM),
+            Token::Value(4.0),
+            Token::Op(Operator::DIV),
+            Token::Value(1.5),
+        ];
+
+        assert_eq!(exp, tokens);
+    }
+
+    #[test]
+    fn test_parse_functions() {
+        let constants = Constants { e: E, pi: PI };
+
+        let tree = parse_term::<f32>("ncr(5, 2) + 3!", &constants).unwrap();
+        assert_eq!(Some(&Token::add()), at(&tree, &[]));
+        assert_eq!(Some(&Token::Func(Function::NCR)), at(&tree, &[0]));
+        assert_eq!(Some(&Token::Op(Operator::FAC)), at(&tree, &[1]));
+        assert_eq!(None, at(&tree, &[1, 1]));
+
+        let result = parse_term::<f32>("gcd(4)", &constants);
+        assert!(matches!(
+            result,
+            Err(ParsingError::WrongArgumentCount(_, 2, 1))
+        ));
+
+        let result = parse_term::<f32>("isprime 7", &constants);
+        assert!(matches!(result, Err(ParsingError::MissingArguments(_))));
+    }
+
+    #[test]
+    fn test_parse_conditional() {
+        let constants = Constants { e: E, pi: PI };
+
+        let tree = parse_term::<f32>("x < 1 ? 2 : y ? 3 : 4", &constants).unwrap();
+        assert_eq!(Some(&Token::Op(Operator::COND)), at(&tree, &[]));
+        assert_eq!(Some(&Token::Op(Operator::LT)), at(&tree, &[0]));
+        assert_eq!(Some(&Token::Value(2.0)), at(&tree, &[1]));
+        assert_eq!(Some(&Token::Op(Operator::COND)), at(&tree, &[2]));
+
+        let tree = parse_term::<f32>("a ? b ? 1 : 2 : 3", &constants).unwrap();
+        assert_eq!(Some(&Token::Op(Operator::COND)), at(&tree, &[1]));
+        assert_eq!(Some(&Token::Value(3.0)), at(&tree, &[2]));
+
+        let result = parse_term::<f32>("a ? 1", &constants);
+        assert!(matches!(result, Err(ParsingError::IncompleteConditional)));
+        let result = parse_term::<f32>("1 : 2", &constants);
+        assert!(matches!(result, Err(ParsingError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_temporal_literals_as_numbers() {
+        let constants = Constants {
+            e: std::f64::consts::E,
+            pi: std::f64::consts::PI,
+        };
+        let calc = |s: &str| calculate_result(&parse_term::<f64>(s, &constants).unwrap());
+        assert_eq!(Ok(10.0), calc("1>0 ? 10:20"));
+        assert_eq!(Ok(20.0), calc("0 ? 10:20"));
+        assert_eq!(Ok(1957.0), calc("2000-12-31"));
+    }
+
+    #[test]
+    fn test_parse_equation() {
+        let constants = Constants { e: E, pi: PI };
+
+        let tree = parse_term::<f32>("2x + 3 = y > 1 ? 11 : 0 =", &constants).unwrap();
+        assert_eq!(Some(&Token::Op(Operator::EQ)), at(&tree, &[]));
+        assert_eq!(Some(&Token::Op(Operator::ADD)), at(&tree, &[0]));
+        assert_eq!(Some(&Token::Op(Operator::COND)), at(&tree, &[1]));
+
+        let tree = parse_term::<f32>("solve(x^2 = 2, x)", &constants).unwrap();
+        assert_eq!(Some(&Token::Op(Operator::EQ)), at(&tree, &[0]));
+        assert_eq!(Some(&Token::Var("x".to_owned())), at(&tree, &[1]));
+
+        let result = parse_term::<f32>("1 = 2 = 3", &constants);
+        assert!(matches!(result, Err(ParsingError::InvalidInput(_))));
+        let result = parse_term::<f32>("= 3", &constants);
+        assert!(matches!(result, Err(ParsingError::MissingOperand(_))));
+    }
+
+    #[test]
+    fn test_implicit_multiplication() {
+        let constants = Constants { e: E, pi: PI };
+
+        let mut tokens = tokenize_term::<f32>("2pi", &constants).unwrap();
+        insert_implicit_multiplication(&mut tokens);
+        assert_eq!(
+            vec![Token::Value(2.0), Token::mul(), Token::Value(PI)],
+            tokens
+        );
+
+        let mut tokens = tokenize_term::<f32>("(a + b)(a - b) 3!x", &constants).unwrap();
+        insert_implicit_multiplication(&mut tokens);
+        let muls: Vec<usize> = (0..tokens.len()).filter(|&i| tokens[i].is_mul()).collect();
+        assert_eq!(vec![5, 11, 14], muls);
+
+        let tree = parse_term::<f32>("3(4 + 5)", &constants).unwrap();
+        assert_eq!(Some(&Token::mul()), at(&tree, &[]));
+        assert_eq!(Some(&Token::add()), at(&tree, &[1]));
+
+        let options = ParseOptions {
+            implicit_multiplication: false,
+        };
+        let result = parse_term_with_options::<f32>("2pi", &constants, &options);
+        assert!(matches!(result, Err(ParsingError::EmptyTree)));
+    }
+
+    #[test]
+    fn test_parse_is_linear() {
+        let constants = Constants { e: E, pi: PI };
+
+        for n in [30, 1000] {
+            let term = vec!["1"; n].join(" + ");
+            let tree = parse_term::<f32>(&term, &constants).unwrap();
+            assert_eq!(2 * n - 1, tree.len());
+        }
+    }
+
+    #[test]
+    fn test_parse_unbalanced() {
+        let constants = Constants { e: E, pi: PI };
+        let result = parse_term::<f32>("(3 + 4))", &constants);
+
+        assert!(matches!(result, Err(ParsingError::UnbalancedParentheses)));
+    }
 }
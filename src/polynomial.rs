@@ -0,0 +1,605 @@
+//! Polynomials in any number of variables, expanded from and written back to terms.
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    ops::{Add, Mul, Neg, Sub},
+};
+
+use crate::{
+    calculate::{CalculationError, Variables, calculate_subtree},
+    number_theory,
+    token::{Operator, Token},
+    traits::Numeric,
+    tree::{NodeId, Tree},
+};
+
+/// Powers above this are left unexpanded.
+pub const MAX_DEGREE: u32 = 64;
+
+/// Exponents of the variables of a term, sorted by name and without zero exponents.
+type Monomial = Vec<(String, u32)>;
+
+/// Sum of monomials with their non-zero coefficients, the normal form of a polynomial.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial<T: Numeric> {
+    terms: BTreeMap<Monomial, T>,
+}
+
+fn multiply_monomials(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut exponents: BTreeMap<String, u32> = a.iter().cloned().collect();
+    for (name, e) in b {
+        *exponents.entry(name.clone()).or_default() += e;
+    }
+    exponents.into_iter().collect()
+}
+
+fn exponent(m: &Monomial, var: &str) -> u32 {
+    m.iter()
+        .find(|(name, _)| name == var)
+        .map_or(0, |&(_, e)| e)
+}
+
+/// Higher total degree first, then higher powers of the alphabetically first variables.
+fn graded_order(a: &Monomial, b: &Monomial) -> Ordering {
+    let total = |m: &Monomial| m.iter().map(|&(_, e)| e).sum::<u32>();
+    let names: BTreeSet<&String> = a.iter().chain(b).map(|(name, _)| name).collect();
+    total(b).cmp(&total(a)).then_with(|| {
+        names
+            .into_iter()
+            .map(|name| exponent(b, name).cmp(&exponent(a, name)))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    })
+}
+
+impl<T: Numeric> Polynomial<T> {
+    pub fn zero() -> Self {
+        Polynomial {
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn constant(c: T) -> Self {
+        Self::monomial(c, vec![])
+    }
+
+    pub fn variable(name: &str) -> Self {
+        Self::monomial(T::one(), vec![(name.to_owned(), 1)])
+    }
+
+    fn monomial(c: T, m: Monomial) -> Self {
+        let mut p = Self::zero();
+        if c != T::default() {
+            p.terms.insert(m, c);
+        }
+        p
+    }
+
+    /// Adds `c` to the coefficient of `m`, dropping it if it becomes zero.
+    fn accumulate(&mut self, m: Monomial, c: T) -> Result<(), CalculationError> {
+        let sum = match self.terms.get(&m) {
+            Some(&a) => a
+                .checked_add(c)
+                .ok_or(CalculationError::Overflow(Operator::ADD))?,
+            None => c,
+        };
+        match sum == T::default() {
+            true => self.terms.remove(&m),
+            false => self.terms.insert(m, sum),
+        };
+        Ok(())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The value of a polynomial without variables.
+    pub fn as_constant(&self) -> Option<T> {
+        match self.terms.len() {
+            0 => Some(T::default()),
+            1 => self.terms.get(&vec![]).copied(),
+            _ => None,
+        }
+    }
+
+    pub fn variables(&self) -> BTreeSet<String> {
+        self.terms
+            .keys()
+            .flatten()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Highest power of `var`, zero if it doesn't occur.
+    pub fn degree(&self, var: &str) -> u32 {
+        self.terms
+            .keys()
+            .map(|m| exponent(m, var))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Coefficients of the powers of `var`, lowest first. They are polynomials in the other variables.
+    pub fn coefficients(&self, var: &str) -> Vec<Polynomial<T>> {
+        let mut coefficients = vec![Self::zero(); self.degree(var) as usize + 1];
+        for (m, &c) in &self.terms {
+            // The other variables tell the terms with the same power of `var` apart.
+            let rest = m.iter().filter(|(name, _)| name != var).cloned().collect();
+            coefficients[exponent(m, var) as usize]
+                .terms
+                .insert(rest, c);
+        }
+        coefficients
+    }
+
+    pub fn pow(&self, n: u32) -> Result<Self, CalculationError> {
+        (0..n).try_fold(Self::constant(T::one()), |power, _| &power * self)
+    }
+
+    /// Divides every coefficient by `d`, `None` if a division of integers isn't exact.
+    /// Other types take the rounded quotient.
+    fn divide(&self, d: T) -> Option<Self> {
+        let exact = T::epsilon() == 0.0;
+        let mut quotient = Self::zero();
+        for (m, &c) in &self.terms {
+            let q = c.quotient(d).filter(|&q| !exact || q * d == c)?;
+            if q != T::default() {
+                quotient.terms.insert(m.clone(), q);
+            }
+        }
+        Some(quotient)
+    }
+
+    /// Normal form of a polynomial term, see [`Polynomial::from_subtree`].
+    pub fn from_term(term: &Tree<Token<T>>) -> Result<Self, CalculationError> {
+        let Some(root) = term.root() else {
+            return Ok(Self::zero());
+        };
+        Self::from_subtree(term, root, &Variables::new())?
+            .ok_or_else(|| CalculationError::NotPolynomial(term.to_string()))
+    }
+
+    /// Normal form of the subtree at node `k`, `None` if it isn't a polynomial. Variables with
+    /// a value in `variables` and subterms without variables count as constants, only
+    /// integer powers up to [`MAX_DEGREE`] and divisions by constants are expanded.
+    pub(crate) fn from_subtree(
+        term: &Tree<Token<T>>,
+        k: NodeId,
+        variables: &Variables<T>,
+    ) -> Result<Option<Self>, CalculationError> {
+        let mut polynomials: Vec<Option<Self>> = vec![None; term.len()];
+        let mut result = Ok(());
+        term.traverse_postorder(k, &mut |(id, token)| {
+            if result.is_err() {
+                return;
+            }
+            let children: Vec<Option<&Self>> = term
+                .children(id)
+                .iter()
+                .map(|&child| polynomials[child].as_ref())
+                .collect();
+            let polynomial = match (token, children.as_slice()) {
+                (Token::Var(name), _) if !variables.contains_key(name) => {
+                    Ok(Some(Self::variable(name)))
+                }
+                (Token::Op(Operator::ADD), &[Some(p), Some(q)]) => (p + q).map(Some),
+                (Token::Op(Operator::SUB), &[Some(p), Some(q)]) => (p - q).map(Some),
+                (Token::Op(Operator::NEG), &[Some(p)]) => (-p).map(Some),
+                (Token::Op(Operator::MUL), &[Some(p), Some(q)]) => (p * q).map(Some),
+                (Token::Op(Operator::DIV), &[Some(p), Some(d)])
+                    if p.as_constant().is_none() && d.as_constant().is_some() =>
+                {
+                    Ok(d.as_constant()
+                        .filter(|&d| d != T::default())
+                        .and_then(|d| p.divide(d)))
+                }
+                (Token::Op(Operator::POW), &[Some(p), Some(n)])
+                    if p.as_constant().is_none() && n.as_constant().is_some() =>
+                {
+                    let n = n.as_constant().unwrap_or_default().to_f64();
+                    match n >= 0.0 && n.fract() == 0.0 && n <= MAX_DEGREE as f64 {
+                        true => p.pow(n as u32).map(Some),
+                        false => Ok(None),
+                    }
+                }
+                _ if children
+                    .iter()
+                    .all(|p| p.is_some_and(|p| p.as_constant().is_some())) =>
+                {
+                    calculate_subtree(term, id, variables).map(|c| Some(Self::constant(c)))
+                }
+                _ => Ok(None),
+            };
+            match polynomial {
+                Ok(polynomial) => polynomials[id] = polynomial,
+                Err(err) => result = Err(err),
+            }
+        });
+        result.map(|_| polynomials[k].take())
+    }
+
+    /// Writes the polynomial into `out` as a sum of products, terms of higher degree first.
+    pub(crate) fn write_term(&self, out: &mut Tree<Token<T>>) -> NodeId {
+        let mut terms: Vec<(&Monomial, T)> = self.terms.iter().map(|(m, &c)| (m, c)).collect();
+        terms.sort_by(|(a, _), (b, _)| graded_order(a, b));
+
+        let mut sum: Option<NodeId> = None;
+        for (m, c) in terms {
+            let negative = c < T::default() && sum.is_some();
+            let c = if negative { -c } else { c };
+            let mut factors = vec![];
+            if (c != T::one() && c != -T::one()) || m.is_empty() {
+                factors.push(out.push(Token::Value(c), vec![]));
+            }
+            for (name, e) in m {
+                let var = out.push(Token::Var(name.clone()), vec![]);
+                factors.push(match e {
+                    1 => var,
+                    _ => {
+                        let e = out.push(
+                            Token::Value(T::from_f64(*e as f64).unwrap_or_default()),
+                            vec![],
+                        );
+                        out.push(Token::Op(Operator::POW), vec![var, e])
+                    }
+                });
+            }
+            let mut product = factors
+                .into_iter()
+                .reduce(|a, b| out.push(Token::Op(Operator::MUL), vec![a, b]))
+                .expect("a term has a coefficient or a variable");
+            if c == -T::one() && !m.is_empty() {
+                product = out.push(Token::Op(Operator::NEG), vec![product]);
+            }
+            sum = Some(match sum {
+                None => product,
+                Some(sum) if negative => out.push(Token::Op(Operator::SUB), vec![sum, product]),
+                Some(sum) => out.push(Token::Op(Operator::ADD), vec![sum, product]),
+            });
+        }
+        sum.unwrap_or_else(|| out.push(Token::Value(T::default()), vec![]))
+    }
+
+    pub fn to_term(&self) -> Tree<Token<T>> {
+        let mut tree = Tree::new();
+        let root = self.write_term(&mut tree);
+        tree.set_root(root);
+        tree
+    }
+}
+
+/// The arithmetic of polynomials fails with [`CalculationError::Overflow`] where
+/// the arithmetic of their coefficients overflows.
+impl<T: Numeric> Add for &Polynomial<T> {
+    type Output = Result<Polynomial<T>, CalculationError>;
+
+    fn add(self, other: Self) -> Self::Output {
+        let mut sum = self.clone();
+        for (m, &c) in &other.terms {
+            sum.accumulate(m.clone(), c)?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<T: Numeric> Neg for &Polynomial<T> {
+    type Output = Result<Polynomial<T>, CalculationError>;
+
+    fn neg(self) -> Self::Output {
+        let terms = self
+            .terms
+            .iter()
+            .map(|(m, &c)| Some((m.clone(), c.checked_neg()?)))
+            .collect::<Option<_>>()
+            .ok_or(CalculationError::Overflow(Operator::NEG))?;
+        Ok(Polynomial { terms })
+    }
+}
+
+impl<T: Numeric> Sub for &Polynomial<T> {
+    type Output = Result<Polynomial<T>, CalculationError>;
+
+    fn sub(self, other: Self) -> Self::Output {
+        self + &(-other)?
+    }
+}
+
+impl<T: Numeric> Mul for &Polynomial<T> {
+    type Output = Result<Polynomial<T>, CalculationError>;
+
+    fn mul(self, other: Self) -> Self::Output {
+        let mut product = Polynomial::zero();
+        for (a, &c) in &self.terms {
+            for (b, &d) in &other.terms {
+                let cd = c
+                    .checked_mul(d)
+                    .ok_or(CalculationError::Overflow(Operator::MUL))?;
+                product.accumulate(multiply_monomials(a, b), cd)?;
+            }
+        }
+        Ok(product)
+    }
+}
+
+/// Expanded form of the subtree at node `k`.
+pub(crate) fn expand_at<T: Numeric>(
+    term: &Tree<Token<T>>,
+    k: NodeId,
+) -> Result<Tree<Token<T>>, CalculationError> {
+    let polynomial = Polynomial::from_subtree(term, k, &Variables::new())?;
+    match polynomial {
+        Some(polynomial) => Ok(polynomial.to_term()),
+        None => Err(CalculationError::NotPolynomial(
+            term.subtree(k).map(|t| t.to_string()).unwrap_or_default(),
+        )),
+    }
+}
+
+/// Multiplies out products and integer powers of a polynomial term, `(x + 1)^3` becomes
+/// `x^3 + 3 * x^2 + 3 * x + 1`.
+pub fn expand<T: Numeric>(term: &Tree<Token<T>>) -> Result<Tree<Token<T>>, CalculationError> {
+    Ok(Polynomial::from_term(term)?.to_term())
+}
+
+fn divisors(n: i128) -> Vec<i128> {
+    let mut divisors = vec![1];
+    let factors = number_theory::prime_factors(n.unsigned_abs() as u64);
+    let mut i = 0;
+    while i < factors.len() {
+        let p = factors[i];
+        let count = factors[i..].iter().take_while(|&&q| q == p).count();
+        let mut powers = vec![];
+        for d in &divisors {
+            let mut d = *d;
+            for _ in 0..count {
+                d *= p as i128;
+                powers.push(d);
+            }
+        }
+        divisors.extend(powers);
+        i += count;
+    }
+    divisors
+}
+
+/// `den^n p(num / den)`, which is zero iff `num / den` is a root of `p`.
+fn scaled_value(p: &[i128], num: i128, den: i128) -> Option<i128> {
+    let n = p.len() as u32 - 1;
+    p.iter().enumerate().try_fold(0i128, |sum, (i, &c)| {
+        let term = c
+            .checked_mul(num.checked_pow(i as u32)?)?
+            .checked_mul(den.checked_pow(n - i as u32)?)?;
+        sum.checked_add(term)
+    })
+}
+
+/// Quotient of `p` by `den x - num`, which has to divide it.
+fn divide_linear(p: &[i128], num: i128, den: i128) -> Vec<i128> {
+    let n = p.len() - 1;
+    let mut q = vec![0; n];
+    q[n - 1] = p[n] / den;
+    for i in (1..n).rev() {
+        q[i - 1] = (p[i] + num * q[i]) / den;
+    }
+    q
+}
+
+/// Splits an integer polynomial into its content and the linear factors `den x - num`
+/// of its rational roots with their multiplicities. The last factor is what remains.
+fn factor_integer(mut p: Vec<i128>) -> (i128, Vec<(Vec<i128>, u32)>) {
+    let leading = *p.last().expect("the polynomial isn't zero");
+    let content = p
+        .iter()
+        .fold(0, |g, &c| number_theory::gcd(g, c.unsigned_abs() as u64)) as i128
+        * leading.signum();
+    p.iter_mut().for_each(|c| *c /= content);
+
+    let mut factors = vec![];
+    let zeros = p.iter().take_while(|&&c| c == 0).count();
+    if zeros > 0 {
+        factors.push((vec![0, 1], zeros as u32));
+        p.drain(..zeros);
+    }
+
+    let mut roots = vec![];
+    for num in divisors(p[0]) {
+        for den in divisors(*p.last().unwrap_or(&1)) {
+            if number_theory::gcd(num as u64, den as u64) == 1 {
+                roots.extend([(num, den), (-num, den)]);
+            }
+        }
+    }
+    // (x - 1) before (x + 1)
+    roots.sort_by(|(a, b), (c, d)| (c * b).cmp(&(a * d)));
+    for (num, den) in roots {
+        let mut multiplicity = 0;
+        while p.len() > 1 && scaled_value(&p, num, den) == Some(0) {
+            p = divide_linear(&p, num, den);
+            multiplicity += 1;
+        }
+        if multiplicity > 0 {
+            factors.push((vec![-num, den], multiplicity));
+        }
+    }
+    if p.len() > 1 {
+        factors.push((p, 1));
+    }
+    (content, factors)
+}
+
+/// Factored form of the subtree at node `k`.
+pub(crate) fn factor_at<T: Numeric>(
+    term: &Tree<Token<T>>,
+    k: NodeId,
+) -> Result<Tree<Token<T>>, CalculationError> {
+    let not_factorable = || {
+        CalculationError::NotFactorable(term.subtree(k).map(|t| t.to_string()).unwrap_or_default())
+    };
+    let polynomial =
+        Polynomial::from_subtree(term, k, &Variables::new())?.ok_or_else(not_factorable)?;
+    let variables = polynomial.variables();
+    let [var] = variables.iter().collect::<Vec<_>>()[..] else {
+        return Err(not_factorable());
+    };
+
+    // Integer coefficients that f64 still represents exactly.
+    let integers: Option<Vec<i128>> = polynomial
+        .coefficients(var)
+        .iter()
+        .map(|c| {
+            let c = c.as_constant()?.to_f64();
+            (c.fract() == 0.0 && c.abs() < 2f64.powi(53)).then_some(c as i128)
+        })
+        .collect();
+    let (content, factors) = factor_integer(integers.ok_or_else(not_factorable)?);
+
+    let mut out = Tree::new();
+    let to_t = |c: i128| T::from_f64(c as f64).ok_or_else(not_factorable);
+    let mut product = vec![];
+    if content != 1 {
+        product.push(out.push(Token::Value(to_t(content)?), vec![]));
+    }
+    for (coefficients, multiplicity) in factors {
+        let mut factor = Polynomial::zero();
+        for (i, c) in coefficients.into_iter().enumerate() {
+            let monomial = match i {
+                0 => vec![],
+                _ => vec![(var.clone(), i as u32)],
+            };
+            factor.accumulate(monomial, to_t(c)?)?;
+        }
+        let mut id = factor.write_term(&mut out);
+        if multiplicity > 1 {
+            let n = out.push(Token::Value(to_t(multiplicity as i128)?), vec![]);
+            id = out.push(Token::Op(Operator::POW), vec![id, n]);
+        }
+        product.push(id);
+    }
+    let root = product
+        .into_iter()
+        .reduce(|a, b| out.push(Token::Op(Operator::MUL), vec![a, b]))
+        .ok_or_else(not_factorable)?;
+    out.set_root(root);
+    Ok(out)
+}
+
+/// Factors a polynomial in one variable with integer coefficients into its content and the
+/// linear factors of its rational roots, `2x^3 - 2x` becomes `2 * x * (x - 1) * (x + 1)`.
+/// What has no rational roots is left as it is.
+pub fn factor<T: Numeric>(term: &Tree<Token<T>>) -> Result<Tree<Token<T>>, CalculationError> {
+    match term.root() {
+        Some(root) => factor_at(term, root),
+        None => Err(CalculationError::NotFactorable(String::new())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_term, traits::Constants};
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    fn expanded(s: &str) -> String {
+        expand(&parse_term(s, &CONSTANTS).unwrap())
+            .unwrap()
+            .to_string()
+    }
+
+    fn factored(s: &str) -> String {
+        factor(&parse_term(s, &CONSTANTS).unwrap())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_normal_form() {
+        let term = parse_term("(x + y)^2 - 2y(x - 1) + 3", &CONSTANTS).unwrap();
+        let polynomial = Polynomial::from_term(&term).unwrap();
+        assert_eq!(2, polynomial.degree("x"));
+        assert_eq!(
+            vec!["x", "y"],
+            polynomial.variables().into_iter().collect::<Vec<_>>()
+        );
+
+        let coefficients = polynomial.coefficients("x");
+        assert_eq!("y^2 + 2 * y + 3", coefficients[0].to_term().to_string());
+        assert_eq!(Polynomial::zero(), coefficients[1]);
+        assert_eq!(Some(1.0), coefficients[2].as_constant());
+
+        assert_eq!(
+            Err(CalculationError::NotPolynomial("sin(x) + 1".to_owned())),
+            Polynomial::from_term(&parse_term("sin(x) + 1", &CONSTANTS).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!("x^3 + 3 * x^2 + 3 * x + 1", expanded("(x + 1)^3"));
+        assert_eq!("x^2 - y^2", expanded("(x + y)(x - y)"));
+        assert_eq!(
+            "-x^2 + 3 * x - 4",
+            expanded("(x - 2) * (4 - x) / 2 - x^2 / 2")
+        );
+        assert_eq!("2 * x * y + 2", expanded("(x * y + 1) * sqrt(4)"));
+        assert_eq!("0", expanded("(x - 1)^2 - x^2 + 2x - 1"));
+        assert_eq!("x^2 * y^3 * z", expanded("x * z * y^2 * x * y"));
+        assert_eq!(format!("{} * x", 1.0 / 49.0), expanded("x / 49"));
+        assert_eq!(
+            format!("{} * x + {}", 1.0 / 7.0, 1.0 / 7.0),
+            expanded("(x + 1) / 7")
+        );
+    }
+
+    #[test]
+    fn test_expand_integers() {
+        let constants = Constants::<i64> { e: 2, pi: 3 };
+        let expanded = |s: &str| expand(&parse_term(s, &constants).unwrap()).map(|t| t.to_string());
+        assert_eq!(Ok("x + 2".to_owned()), expanded("(2x + 4) / 2"));
+        assert!(matches!(
+            expanded("x / 3"),
+            Err(CalculationError::NotPolynomial(_))
+        ));
+        assert!(matches!(
+            expanded("(x + 2)^64"),
+            Err(CalculationError::Overflow(_))
+        ));
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::MUL)),
+            expanded("9223372036854775807x * 2")
+        );
+        assert_eq!(
+            Err(CalculationError::Overflow(Operator::NEG)),
+            expanded("x - (0 - 9223372036854775807 - 1)")
+        );
+    }
+
+    #[test]
+    fn test_factor() {
+        assert_eq!("(x - 1) * (x + 1)", factored("x^2 - 1"));
+        assert_eq!("2 * x * (x - 1) * (x + 1)", factored("2x^3 - 2x"));
+        assert_eq!("(x + 1)^3", factored("x^3 + 3x^2 + 3x + 1"));
+        assert_eq!("(2 * x - 1) * (3 * x + 2)", factored("6x^2 + x - 2"));
+        assert_eq!("-1 * (x - 3) * (x^2 + 1)", factored("-x^3 + 3x^2 - x + 3"));
+        assert_eq!("y^2 + 2", factored("y^2 + 2"));
+
+        for term in ["x * y - 1", "x^2 - 0.5", "2"] {
+            let result = factor(&parse_term(term, &CONSTANTS).unwrap());
+            assert!(
+                matches!(result, Err(CalculationError::NotFactorable(_))),
+                "{term}"
+            );
+        }
+
+        let term = parse_term::<i64>("x^4 - 16", &Constants { e: 3, pi: 3 }).unwrap();
+        assert_eq!(
+            "(x - 2) * (x + 2) * (x^2 + 4)",
+            factor(&term).unwrap().to_string()
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Diagrams of parsed terms, as Graphviz DOT and as ASCII art for the terminal.
+
+use crate::{
+    token::Token,
+    traits::Numeric,
+    tree::{NodeId, Tree},
+};
+
+/// Graphviz description of the tree, render it with `dot -Tsvg`.
+/// Nodes are named after their ids and labelled with their token.
+pub fn to_dot<T: Numeric>(tree: &Tree<Token<T>>) -> String {
+    let mut out = String::from("digraph term {\n    node [shape=circle];\n");
+    let Some(root) = tree.root() else {
+        return out + "}\n";
+    };
+
+    tree.traverse_preorder(root, &mut |(id, token)| {
+        let label = token.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+        out += &format!("    n{id} [label=\"{label}\"];\n");
+        for child in tree.children(id) {
+            out += &format!("    n{id} -> n{child};\n");
+        }
+    });
+    out + "}\n"
+}
+
+/// Tree diagram in the style of the `tree` command, one token per line
+/// with the operands below their operator.
+///
+/// ```text
+/// *
+/// ├── +
+/// │   ├── 3
+/// │   └── 4
+/// └── 5
+/// ```
+pub fn to_ascii<T: Numeric>(tree: &Tree<Token<T>>) -> String {
+    let mut out = String::new();
+    let Some(root) = tree.root() else {
+        return out;
+    };
+
+    // Node, the indentation of its line and whether it is the last of its siblings.
+    let mut stack: Vec<(NodeId, String, Option<bool>)> = vec![(root, String::new(), None)];
+    while let Some((id, indent, last)) = stack.pop() {
+        let Some(token) = tree.get(id) else {
+            continue;
+        };
+        let (branch, continued) = match last {
+            None => ("", ""),
+            Some(false) => ("├── ", "│   "),
+            Some(true) => ("└── ", "    "),
+        };
+        out += &format!("{indent}{branch}{token}\n");
+
+        let indent = indent + continued;
+        let children = tree.children(id);
+        for (n, &child) in children.iter().enumerate().rev() {
+            stack.push((child, indent.clone(), Some(n + 1 == children.len())));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_term, traits::Constants};
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    #[test]
+    fn test_ascii() {
+        let tree = parse_term("(3 + 4) * -x - gcd(4, 6)", &CONSTANTS).unwrap();
+        let expected = "\
+-
+├── *
+│   ├── +
+│   │   ├── 3
+│   │   └── 4
+│   └── -
+│       └── x
+└── gcd
+    ├── 4
+    └── 6
+";
+        assert_eq!(expected, to_ascii(&tree));
+        assert_eq!("", to_ascii(&Tree::<Token<f64>>::new()));
+    }
+
+    #[test]
+    fn test_dot() {
+        let tree = parse_term("3 + 4!", &CONSTANTS).unwrap();
+        let dot = to_dot(&tree);
+        let root = tree.root().unwrap();
+        let fac = tree.child(root, 1).unwrap();
+
+        assert!(dot.starts_with("digraph term {"));
+        assert!(dot.contains(&format!("    n{root} [label=\"+\"];")));
+        assert!(dot.contains(&format!("    n{fac} [label=\"!\"];")));
+        assert!(dot.contains(&format!("    n{root} -> n{fac};")));
+        assert_eq!(4, dot.matches("label").count());
+        assert!(dot.ends_with("}\n"));
+    }
+}
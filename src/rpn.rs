@@ -0,0 +1,247 @@
+//! Reverse Polish notation, words separated by whitespace like `3 4 + 5 *`.
+//! Operators and functions take their operands from the stack, `neg` and `not`
+//! stand for the prefix `-` and `!`.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{
+    calculate::{CalculationError, calculate_result},
+    parse::{ParsingError, reduce, word_token},
+    token::Token,
+    traits::{Constants, Numeric},
+    tree::Tree,
+};
+
+#[derive(Error, Debug)]
+pub enum RpnError {
+    #[error(transparent)]
+    Parsing(#[from] ParsingError),
+    #[error(transparent)]
+    Calculation(#[from] CalculationError),
+}
+
+/// Words that rearrange the stack instead of computing something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum StackOp {
+    /// Exchanges the two topmost entries.
+    SWAP,
+    /// Removes the topmost entry.
+    DROP,
+    /// Pushes a copy of the topmost entry.
+    DUP,
+    /// Moves the topmost entry to the bottom of the stack.
+    ROLL,
+}
+
+impl StackOp {
+    pub fn apply<X: Clone>(self, stack: &mut Vec<X>) -> Result<(), ParsingError> {
+        let needed = match self {
+            StackOp::SWAP => 2,
+            StackOp::DROP | StackOp::DUP | StackOp::ROLL => 1,
+        };
+        if stack.len() < needed {
+            return Err(ParsingError::MissingOperand(self.to_string()));
+        }
+
+        let top = stack.len() - 1;
+        match self {
+            StackOp::SWAP => stack.swap(top, top - 1),
+            StackOp::DROP => {
+                stack.pop();
+            }
+            StackOp::DUP => stack.push(stack[top].clone()),
+            StackOp::ROLL => stack.rotate_right(1),
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for StackOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            StackOp::SWAP => "swap",
+            StackOp::DROP => "drop",
+            StackOp::DUP => "dup",
+            StackOp::ROLL => "roll",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for StackOp {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => Ok(StackOp::SWAP),
+            "drop" => Ok(StackOp::DROP),
+            "dup" => Ok(StackOp::DUP),
+            "roll" => Ok(StackOp::ROLL),
+            other => Err(ParsingError::InvalidInput(other.to_owned())),
+        }
+    }
+}
+
+/// Parses a term in RPN into the same tree [`parse_term`](crate::parse::parse_term)
+/// builds for its infix form. `dup` lets two operators share an operand subtree.
+pub fn parse_rpn<T: Numeric>(
+    term: &str,
+    constants: &Constants<T>,
+) -> Result<Tree<Token<T>>, ParsingError> {
+    let mut tree = Tree::new();
+    let mut stack = vec![];
+
+    for word in term.to_lowercase().split_whitespace() {
+        if let Ok(op) = StackOp::from_str(word) {
+            op.apply(&mut stack)?;
+            continue;
+        }
+        let t = word_token(word, constants)?;
+        match t.arity() {
+            0 => stack.push(tree.push(t, vec![])),
+            arity => reduce(t, arity, &mut stack, &mut tree)?,
+        }
+    }
+
+    match stack.as_slice() {
+        &[root] => {
+            tree.set_root(root);
+            Ok(tree)
+        }
+        [] => Err(ParsingError::EmptyTree),
+        _ => Err(ParsingError::LeftoverOperands(stack.len())),
+    }
+}
+
+/// Value stack of an HP-style calculator. Numbers are pushed as they are
+/// entered and every operator immediately replaces its operands by the result.
+#[derive(Debug, Clone, Default)]
+pub struct RpnStack<T> {
+    values: Vec<T>,
+}
+
+impl<T: Numeric> RpnStack<T> {
+    pub fn new() -> Self {
+        RpnStack { values: vec![] }
+    }
+
+    /// Entries from the bottom to the top of the stack.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.values.push(value);
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Applies the words of `input` one after another. On an error the stack
+    /// is left as it was before.
+    pub fn enter(&mut self, input: &str, constants: &Constants<T>) -> Result<(), RpnError> {
+        let mut values = self.values.clone();
+        for word in input.to_lowercase().split_whitespace() {
+            if let Ok(op) = StackOp::from_str(word) {
+                op.apply(&mut values)?;
+                continue;
+            }
+            let t = word_token(word, constants)?;
+            let arity = t.arity();
+            if arity == 0 {
+                let Token::Value(value) = t else {
+                    return Err(CalculationError::UnknownVariable(word.to_owned()).into());
+                };
+                values.push(value);
+                continue;
+            }
+            if values.len() < arity {
+                return Err(ParsingError::MissingOperand(t.to_string()).into());
+            }
+
+            // Evaluated as a term of its own, so every operator works like in infix terms.
+            let mut term = Tree::new();
+            let operands = values
+                .split_off(values.len() - arity)
+                .into_iter()
+                .map(|v| term.push(Token::Value(v), vec![]))
+                .collect();
+            let root = term.push(t, operands);
+            term.set_root(root);
+            values.push(calculate_result(&term)?);
+        }
+        self.values = values;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_term, unparse::Notation, unparse::unparse};
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    #[test]
+    fn test_parse_rpn() {
+        let rpn = |s: &str| parse_rpn(s, &CONSTANTS).unwrap();
+
+        assert_eq!(
+            parse_term("(3 + 4) * 5", &CONSTANTS).unwrap(),
+            rpn("3 4 + 5 *")
+        );
+        assert_eq!(
+            "-(a - b) / gcd(4, 6)",
+            rpn("a b - neg 4 6 gcd /").to_string()
+        );
+        assert_eq!("x > 0 ? x : -x", rpn("x 0 > x x neg ?").to_string());
+        assert_eq!("2 - 1", rpn("1 2 swap -").to_string());
+        assert_eq!("x * x", rpn("x dup *").to_string());
+
+        let infix = parse_term("1 - (2 - 3)! * -4", &CONSTANTS).unwrap();
+        let postfix = unparse(&infix, Notation::Postfix);
+        assert_eq!(infix, rpn(&postfix));
+
+        assert!(matches!(
+            parse_rpn("1 2 3 +", &CONSTANTS),
+            Err(ParsingError::LeftoverOperands(2))
+        ));
+        assert!(matches!(
+            parse_rpn("1 +", &CONSTANTS),
+            Err(ParsingError::MissingOperand(_))
+        ));
+        assert!(matches!(
+            parse_rpn("1 ( 2 +", &CONSTANTS),
+            Err(ParsingError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_stack() {
+        let mut stack = RpnStack::new();
+        stack.enter("3 4 + 5 *", &CONSTANTS).unwrap();
+        assert_eq!(&[35.0], stack.values());
+
+        stack.enter("1 2 roll", &CONSTANTS).unwrap();
+        assert_eq!(&[2.0, 35.0, 1.0], stack.values());
+        stack.enter("swap drop dup", &CONSTANTS).unwrap();
+        assert_eq!(&[2.0, 1.0, 1.0], stack.values());
+
+        assert!(matches!(
+            stack.enter("+ 0 /", &CONSTANTS),
+            Err(RpnError::Calculation(CalculationError::DivisionByZero))
+        ));
+        assert!(stack.enter("x", &CONSTANTS).is_err());
+        assert_eq!(&[2.0, 1.0, 1.0], stack.values());
+
+        stack.enter("! 5 2 ncr", &CONSTANTS).unwrap();
+        assert_eq!(&[2.0, 1.0, 1.0, 10.0], stack.values());
+    }
+}
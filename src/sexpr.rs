@@ -0,0 +1,204 @@
+//! S-expressions like `(* (+ 3 4) 5)`, every operation in parentheses with its
+//! operator or function first.
+
+use crate::{
+    parse::{ParsingError, word_token},
+    token::{Operator, Token},
+    traits::{Constants, Numeric},
+    tree::{NodeId, Tree},
+    unparse::polish_symbol,
+};
+
+/// An operation whose closing parenthesis is still ahead.
+struct Frame<T: Numeric> {
+    head: Option<Token<T>>,
+    args: Vec<NodeId>,
+}
+
+/// Joins the arguments of a closed operation under its head. Binary operators
+/// take two or more arguments and fold them from the left, `(- 10 2 3)` is `10 - 2 - 3`.
+/// `-` with a single argument is a negation.
+fn close<T: Numeric>(frame: Frame<T>, tree: &mut Tree<Token<T>>) -> Result<NodeId, ParsingError> {
+    let Some(head) = frame.head else {
+        return Err(ParsingError::InvalidInput("()".to_owned()));
+    };
+    let mut args = frame.args;
+
+    match head {
+        Token::Op(Operator::SUB) if args.len() == 1 => {
+            Ok(tree.push(Token::Op(Operator::NEG), args))
+        }
+        Token::Op(op) if op.arity() == 2 => {
+            if args.len() < 2 {
+                return Err(ParsingError::MissingOperand(head.to_string()));
+            }
+            let rest = args.split_off(2);
+            let first = tree.push(head.clone(), args);
+            Ok(rest
+                .into_iter()
+                .fold(first, |acc, arg| tree.push(head.clone(), vec![acc, arg])))
+        }
+        _ if args.len() != head.arity() => Err(ParsingError::WrongArgumentCount(
+            head.to_string(),
+            head.arity(),
+            args.len(),
+        )),
+        _ => Ok(tree.push(head, args)),
+    }
+}
+
+/// Parses an S-expression into the same tree [`parse_term`](crate::parse::parse_term)
+/// builds for its infix form.
+pub fn parse_sexpr<T: Numeric>(
+    term: &str,
+    constants: &Constants<T>,
+) -> Result<Tree<Token<T>>, ParsingError> {
+    let spaced = term.to_lowercase().replace('(', " ( ").replace(')', " ) ");
+
+    let mut tree = Tree::new();
+    let mut frames: Vec<Frame<T>> = vec![];
+    let mut roots = vec![];
+
+    for word in spaced.split_whitespace() {
+        let id = match word {
+            "(" => {
+                frames.push(Frame {
+                    head: None,
+                    args: vec![],
+                });
+                continue;
+            }
+            ")" => {
+                let frame = frames.pop().ok_or(ParsingError::UnbalancedParentheses)?;
+                close(frame, &mut tree)?
+            }
+            _ => {
+                let t = word_token(word, constants)?;
+                match frames.last_mut() {
+                    Some(frame) if frame.head.is_none() => {
+                        if t.arity() == 0 {
+                            return Err(ParsingError::InvalidInput(word.to_owned()));
+                        }
+                        frame.head = Some(t);
+                        continue;
+                    }
+                    _ if t.arity() > 0 => {
+                        return Err(ParsingError::MissingArguments(word.to_owned()));
+                    }
+                    _ => tree.push(t, vec![]),
+                }
+            }
+        };
+        match frames.last_mut() {
+            Some(frame) => frame.args.push(id),
+            None => roots.push(id),
+        }
+    }
+
+    if !frames.is_empty() {
+        return Err(ParsingError::UnbalancedParentheses);
+    }
+    match roots.as_slice() {
+        &[root] => {
+            tree.set_root(root);
+            Ok(tree)
+        }
+        [] => Err(ParsingError::EmptyTree),
+        _ => Err(ParsingError::LeftoverOperands(roots.len())),
+    }
+}
+
+enum Step {
+    Node(NodeId),
+    Text(&'static str),
+}
+
+/// Writes the tree as an S-expression that [`parse_sexpr`] reads back into the same tree.
+pub fn to_sexpr<T: Numeric>(tree: &Tree<Token<T>>) -> String {
+    let mut out = String::new();
+    let mut stack: Vec<Step> = tree.root().map(Step::Node).into_iter().collect();
+
+    while let Some(step) = stack.pop() {
+        let id = match step {
+            Step::Text(text) => {
+                out += text;
+                continue;
+            }
+            Step::Node(id) => id,
+        };
+        let Some(token) = tree.get(id) else {
+            continue;
+        };
+        let children = tree.children(id);
+        if children.is_empty() {
+            out += &token.to_string();
+            continue;
+        }
+
+        out += &format!("({}", polish_symbol(token));
+        stack.push(Step::Text(")"));
+        for &child in children.iter().rev() {
+            stack.push(Step::Node(child));
+            stack.push(Step::Text(" "));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_term;
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    #[test]
+    fn test_parse_sexpr() {
+        let sexpr = |s: &str| parse_sexpr(s, &CONSTANTS).unwrap();
+
+        assert_eq!(
+            parse_term("(3 + 4) * 5", &CONSTANTS).unwrap(),
+            sexpr("(* (+ 3 4) 5)")
+        );
+        assert_eq!("10 - 2 - x", sexpr("(- 10 2 x)").to_string());
+        assert_eq!("-x! + -y", sexpr("(+ (- (! x)) (neg y))").to_string());
+        assert_eq!("a ? nCr(5, 2) : 0", sexpr("(? a (ncr 5 2) 0)").to_string());
+        assert_eq!("7", sexpr(" 7 ").to_string());
+
+        assert!(matches!(
+            parse_sexpr("(gcd 4)", &CONSTANTS),
+            Err(ParsingError::WrongArgumentCount(_, 2, 1))
+        ));
+        assert!(matches!(
+            parse_sexpr("(+ 1 2", &CONSTANTS),
+            Err(ParsingError::UnbalancedParentheses)
+        ));
+        assert!(matches!(
+            parse_sexpr("(1 2)", &CONSTANTS),
+            Err(ParsingError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            parse_sexpr("(+ 1 +)", &CONSTANTS),
+            Err(ParsingError::MissingArguments(_))
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for term in [
+            "(3 + 4) * (17 + 3 * -4 + 5)",
+            "-(a - b)! / gcd(4, 6) mod 3",
+            "x > 0 && !y ? x : -x",
+        ] {
+            let tree = parse_term(term, &CONSTANTS).unwrap();
+            let printed = to_sexpr(&tree);
+            assert_eq!(tree, parse_sexpr(&printed, &CONSTANTS).unwrap());
+        }
+
+        let tree = parse_term("(1 + 2) * -3 - ncr(5, 2)", &CONSTANTS).unwrap();
+        assert_eq!("(- (* (+ 1 2) (neg 3)) (nCr 5 2))", to_sexpr(&tree));
+    }
+}
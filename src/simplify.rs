@@ -0,0 +1,463 @@
+//! Algebraic simplification of parsed terms. Constant subtrees are folded, sums and
+//! products are flattened, like terms are collected and the operands of commutative
+//! operators are put into a canonical order, so equal terms simplify to equal trees.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    calculate::{Variables, apply_operator, calculate_subtree, variable_name},
+    derivative::derivative_at,
+    polynomial::{expand_at, factor_at},
+    token::{Function, Operator, Token},
+    traits::{Numeric, Truth},
+    tree::{NodeId, Tree},
+    unparse::infix_at,
+};
+
+/// Adds up the constants, each negated if its flag is set. A constant whose sum with
+/// the ones before is undefined or overflows, like a duration after a length, is kept apart.
+fn folded<T: Numeric>(constants: Vec<(bool, T)>) -> Vec<(bool, T)> {
+    let mut sums: Vec<(bool, T)> = vec![];
+    for (negated, c) in constants {
+        let merged = sums.iter_mut().find_map(|(sign, sum)| {
+            let op = match *sign == negated {
+                true => Operator::ADD,
+                false => Operator::SUB,
+            };
+            apply_operator(op, *sum, c).ok().map(|s| *sum = s)
+        });
+        if merged.is_none() {
+            sums.push(match negated {
+                true => {
+                    apply_operator(Operator::SUB, T::default(), c).map_or((true, c), |c| (false, c))
+                }
+                false => (false, c),
+            });
+        }
+    }
+    sums.retain(|&(_, sum)| sum != T::default());
+    sums
+}
+
+/// Multiplies the constants, a constant whose product with the ones before is
+/// undefined or overflows is kept apart.
+fn multiplied<T: Numeric>(constants: Vec<T>) -> Vec<T> {
+    let mut products: Vec<T> = vec![];
+    for c in constants {
+        let merged = products.iter_mut().find_map(|product| {
+            apply_operator(Operator::MUL, *product, c)
+                .ok()
+                .map(|p| *product = p)
+        });
+        if merged.is_none() {
+            products.push(c);
+        }
+    }
+    products
+}
+
+/// Rebuilds a term bottom up into `out`, every node is simplified before its parent.
+struct Simplifier<'a, T: Numeric> {
+    term: &'a Tree<Token<T>>,
+    out: Tree<Token<T>>,
+}
+
+impl<T: Numeric> Simplifier<'_, T> {
+    fn push(&mut self, t: Token<T>, children: Vec<NodeId>) -> NodeId {
+        self.out.push(t, children)
+    }
+
+    fn value(&self, id: NodeId) -> Option<T> {
+        match self.out.get(id) {
+            Some(Token::Value(v)) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn node(&mut self, k: NodeId) -> NodeId {
+        let term = self.term;
+        let Some(token) = term.get(k) else {
+            return self.push(Token::Empty, vec![]);
+        };
+
+        match (token, term.children(k).len()) {
+            (Token::Op(Operator::ADD | Operator::SUB), 2) | (Token::Op(Operator::NEG), 1) => {
+                self.sum(k)
+            }
+            (Token::Op(Operator::MUL), 2) => self.product(k),
+            (Token::Func(func @ (Function::DIFF | Function::EXPAND | Function::FACTOR)), n)
+                if n == func.arity() =>
+            {
+                let children = term.children(k);
+                let rewritten = match func {
+                    Function::DIFF => variable_name(term, children[1], Function::DIFF)
+                        .and_then(|var| derivative_at(term, children[0], var)),
+                    Function::EXPAND => expand_at(term, children[0]),
+                    _ => factor_at(term, children[0]),
+                };
+                match rewritten {
+                    Ok(rewritten) => match rewritten.root() {
+                        Some(root) => self.out.graft(&rewritten, root),
+                        None => self.push(Token::Value(T::default()), vec![]),
+                    },
+                    // Kept as it is, evaluating it reports the error.
+                    Err(_) => {
+                        let children = term.children(k).iter().map(|&c| self.node(c)).collect();
+                        self.push(token.clone(), children)
+                    }
+                }
+            }
+            _ => {
+                let children = term.children(k).iter().map(|&c| self.node(c)).collect();
+                self.apply(token.clone(), children)
+            }
+        }
+    }
+
+    /// Splits a simplified term into its constant factor and the rest,
+    /// `None` if the term is a constant. The factor of a product is its first one.
+    fn coefficient(&mut self, id: NodeId) -> (T, Option<NodeId>) {
+        match (self.out.get(id), self.out.children(id)) {
+            (Some(Token::Value(v)), _) => (*v, None),
+            (Some(Token::Op(Operator::NEG)), &[a]) => {
+                let (c, rest) = self.coefficient(a);
+                match c.checked_neg() {
+                    Some(c) => (c, rest),
+                    // The negation of the smallest integer stays a factor.
+                    None => (T::one(), Some(id)),
+                }
+            }
+            (Some(Token::Op(Operator::MUL)), &[a, b]) => match self.coefficient(a) {
+                (c, None) => (c, Some(b)),
+                (c, Some(rest)) if c != T::one() => {
+                    (c, Some(self.push(Token::mul(), vec![rest, b])))
+                }
+                _ => (T::one(), Some(id)),
+            },
+            _ => (T::one(), Some(id)),
+        }
+    }
+
+    /// `c * id` with `c` in front of the first factor, so a product stays one chain.
+    /// A factor of one is left out and minus one becomes a negation.
+    fn scale(&mut self, c: T, id: NodeId) -> NodeId {
+        if c == T::one() {
+            return id;
+        }
+        match (self.out.get(id), self.out.children(id)) {
+            (Some(Token::Op(Operator::MUL)), &[a, b]) => {
+                let a = self.scale(c, a);
+                self.push(Token::mul(), vec![a, b])
+            }
+            _ if c == -T::one() => self.push(Token::Op(Operator::NEG), vec![id]),
+            _ => {
+                let c = self.push(Token::Value(c), vec![]);
+                self.push(Token::mul(), vec![c, id])
+            }
+        }
+    }
+
+    /// Flattens the `+`, `-` and negations below `k` into a sum of terms with a
+    /// coefficient each. Terms that print the same are collected, the constant comes last.
+    /// Constants whose sum is undefined, like lengths and durations, are left as they are.
+    fn sum(&mut self, k: NodeId) -> NodeId {
+        let term = self.term;
+        let mut constants: Vec<(bool, T)> = vec![];
+        let mut terms: BTreeMap<String, (Vec<(bool, T)>, NodeId)> = BTreeMap::new();
+
+        // The flag marks nodes of `out`, a simplified operand can be a sum itself.
+        let mut stack = vec![(k, false, false)];
+        while let Some((id, negated, simplified)) = stack.pop() {
+            let tree = if simplified { &self.out } else { term };
+            match (tree.get(id), tree.children(id)) {
+                (Some(Token::Op(Operator::ADD)), &[a, b]) => {
+                    stack.push((b, negated, simplified));
+                    stack.push((a, negated, simplified));
+                }
+                (Some(Token::Op(Operator::SUB)), &[a, b]) => {
+                    stack.push((b, !negated, simplified));
+                    stack.push((a, negated, simplified));
+                }
+                (Some(Token::Op(Operator::NEG)), &[a]) => stack.push((a, !negated, simplified)),
+                _ if !simplified => {
+                    let id = self.node(id);
+                    stack.push((id, negated, true));
+                }
+                _ => match self.coefficient(id) {
+                    (c, None) => constants.push((negated, c)),
+                    (c, Some(rest)) => terms
+                        .entry(infix_at(&self.out, rest))
+                        .or_insert((vec![], rest))
+                        .0
+                        .push((negated, c)),
+                },
+            }
+        }
+
+        let mut summands = vec![];
+        for (coefficients, id) in terms.into_values() {
+            let coefficients = folded(coefficients).into_iter();
+            summands.extend(coefficients.map(|(negated, c)| (negated, c, Some(id))));
+        }
+        let constants = folded(constants).into_iter();
+        summands.extend(constants.map(|(negated, c)| (negated, c, None)));
+
+        let mut result = None;
+        for (negated, c, id) in summands {
+            // After the first summand a negative coefficient becomes a subtraction.
+            let (negated, c) = match (result, c.checked_neg()) {
+                (Some(_), Some(minus)) if c < T::default() => (!negated, minus),
+                _ => (negated, c),
+            };
+            let id = match id {
+                Some(id) => self.scale(c, id),
+                None => self.push(Token::Value(c), vec![]),
+            };
+            result = Some(match (result, negated) {
+                (None, false) => id,
+                (None, true) => self.push(Token::Op(Operator::NEG), vec![id]),
+                (Some(acc), false) => self.push(Token::add(), vec![acc, id]),
+                (Some(acc), true) => self.push(Token::sub(), vec![acc, id]),
+            });
+        }
+        result.unwrap_or_else(|| self.push(Token::Value(T::default()), vec![]))
+    }
+
+    /// Splits a simplified factor into its base and a constant exponent.
+    fn exponent(&self, id: NodeId) -> (NodeId, T) {
+        match (self.out.get(id), self.out.children(id)) {
+            (Some(Token::Op(Operator::POW)), &[base, n]) => match self.value(n) {
+                Some(n) => (base, n),
+                None => (id, T::one()),
+            },
+            _ => (id, T::one()),
+        }
+    }
+
+    /// Flattens the `*` below `k` into a constant factor in front of the remaining
+    /// factors in canonical order. Powers of the same base are collected.
+    /// Constants and exponents whose product or sum overflows are left as they are.
+    fn product(&mut self, k: NodeId) -> NodeId {
+        let term = self.term;
+        let mut constants = vec![];
+        let mut factors: BTreeMap<String, (NodeId, Vec<(bool, T)>)> = BTreeMap::new();
+
+        // The flag marks nodes of `out`, a simplified operand can be a product itself.
+        let mut stack = vec![(k, false)];
+        while let Some((id, simplified)) = stack.pop() {
+            let tree = if simplified { &self.out } else { term };
+            match (tree.get(id), tree.children(id)) {
+                (Some(Token::Op(Operator::MUL)), &[a, b]) => {
+                    stack.push((b, simplified));
+                    stack.push((a, simplified));
+                }
+                _ if !simplified => {
+                    let id = self.node(id);
+                    stack.push((id, true));
+                }
+                _ => {
+                    let (c, rest) = self.coefficient(id);
+                    constants.push(c);
+                    if let Some(rest) = rest {
+                        let (base, n) = self.exponent(rest);
+                        factors
+                            .entry(infix_at(&self.out, base))
+                            .or_insert((base, vec![]))
+                            .1
+                            .push((false, n));
+                    }
+                }
+            }
+        }
+
+        let mut constants = multiplied(constants).into_iter();
+        let coefficient = constants.next().unwrap_or(T::one());
+        if coefficient == T::default() {
+            return self.push(Token::Value(coefficient), vec![]);
+        }
+        let mut product = vec![];
+        for (base, exponents) in factors.into_values() {
+            for (_, n) in folded(exponents) {
+                product.push(match n == T::one() {
+                    true => base,
+                    false => {
+                        let n = self.push(Token::Value(n), vec![]);
+                        self.push(Token::Op(Operator::POW), vec![base, n])
+                    }
+                });
+            }
+        }
+        // Constants that are kept apart come last, the coefficient keeps its place in front.
+        for c in constants {
+            product.push(self.push(Token::Value(c), vec![]));
+        }
+        let product = product
+            .into_iter()
+            .reduce(|acc, id| self.push(Token::mul(), vec![acc, id]));
+        match product {
+            Some(id) => self.scale(coefficient, id),
+            None => self.push(Token::Value(coefficient), vec![]),
+        }
+    }
+
+    /// Joins simplified children under `t`, applying the identities that don't
+    /// need the whole term and folding the node if all its operands are constant.
+    fn apply(&mut self, t: Token<T>, mut children: Vec<NodeId>) -> NodeId {
+        match (&t, children.as_slice()) {
+            (Token::Op(Operator::COND), &[c, a, b]) => {
+                if let Some(c) = self.value(c) {
+                    return if c.is_true() { a } else { b };
+                }
+            }
+            (Token::Op(Operator::AND), &[a, _]) if self.value(a).is_some_and(|v| !v.is_true()) => {
+                return self.push(Token::Value(T::default()), vec![]);
+            }
+            (Token::Op(Operator::OR), &[a, _]) if self.value(a).is_some_and(|v| v.is_true()) => {
+                return self.push(Token::Value(T::one()), vec![]);
+            }
+            (Token::Op(Operator::DIV), &[a, b]) if self.value(b) == Some(T::one()) => return a,
+            (Token::Op(Operator::POW), &[a, b]) if self.value(b) == Some(T::one()) => return a,
+            (Token::Op(Operator::POW), &[_, b]) if self.value(b) == Some(T::default()) => {
+                return self.push(Token::Value(T::one()), vec![]);
+            }
+            (Token::Op(Operator::IS | Operator::NE), &[a, b])
+                if infix_at(&self.out, b) < infix_at(&self.out, a) =>
+            {
+                children.swap(0, 1);
+            }
+            _ => {}
+        }
+
+        let constant = !children.is_empty() && children.iter().all(|&c| self.value(c).is_some());
+        let id = self.push(t, children);
+        if constant {
+            // Terms that can't be evaluated, like a division by zero, are kept as they are.
+            if let Ok(v) = calculate_subtree(&self.out, id, &Variables::new()) {
+                return self.push(Token::Value(v), vec![]);
+            }
+        }
+        id
+    }
+}
+
+/// Simplified copy of a term that evaluates to the same value for any variables.
+pub fn simplify<T: Numeric>(term: &Tree<Token<T>>) -> Tree<Token<T>> {
+    let Some(root) = term.root() else {
+        return Tree::new();
+    };
+    let mut simplifier = Simplifier {
+        term,
+        out: Tree::new(),
+    };
+    let root = simplifier.node(root);
+    simplifier.out.subtree(root).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate::{CalculationError, calculate_with_variables},
+        parse::parse_term,
+        traits::Constants,
+        units::Quantity,
+    };
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    fn simplified(s: &str) -> String {
+        simplify(&parse_term(s, &CONSTANTS).unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_identities() {
+        assert_eq!("x", simplified("x * 1 + 0"));
+        assert_eq!("y", simplified("x * 0 + y"));
+        assert_eq!("0", simplified("x - x"));
+        assert_eq!("x", simplified("--x"));
+        assert_eq!("x", simplified("x / (3 - 2)"));
+        assert_eq!("-x", simplified("0 - x"));
+        assert_eq!("x", simplified("1 < 2 ? x : y"));
+        assert_eq!("0", simplified("0 && 1 / 0"));
+    }
+
+    #[test]
+    fn test_folding_and_like_terms() {
+        assert_eq!("6 * x", simplified("2 * x * 3"));
+        assert_eq!("3 * x - 2", simplified("2x + 3 + x - 5"));
+        assert_eq!("2 * x * y", simplified("y * x + x * y"));
+        assert_eq!("-x * y", simplified("x * -y"));
+        assert_eq!("x^3 * y", simplified("x * y * x^2"));
+        assert_eq!("1", simplified("x^0 * x * x^-1"));
+        assert_eq!("a + b + 1", simplified("b + (a + 1)"));
+        assert_eq!("-2 * x + y", simplified("y - 2x"));
+        assert_eq!("x + 6", simplified("gcd(12, 18) + x"));
+        assert_eq!("1", simplified("(1 + 2) * 4 == 12"));
+        assert_eq!("a == b", simplified("b == a"));
+
+        // Terms that fail to evaluate stay in the tree and still fail later.
+        let tree = simplify(&parse_term("x + 1 / 0", &CONSTANTS).unwrap());
+        assert_eq!("1 / 0 + x", tree.to_string());
+        let variables = Variables::from([("x".to_owned(), 1.0)]);
+        assert_eq!(
+            Err(CalculationError::DivisionByZero),
+            calculate_with_variables(&tree, &variables)
+        );
+    }
+
+    #[test]
+    fn test_incommensurable_constants() {
+        let constants = Quantity::<f64>::constants();
+        let simplified = |s: &str| simplify(&parse_term(s, &constants).unwrap()).to_string();
+        assert_eq!("3 m + 2 s", simplified("3 m + 2 s"));
+        assert_eq!("x + 3 m + 2 s", simplified("x + 3 m + 2 s"));
+        assert_eq!("x + 1 m - 2 s", simplified("x + 3 m - 2 s - 2 m"));
+        assert_eq!("3 m * x + 2 s * x", simplified("3 m x + 2 s x"));
+        assert_eq!("5 m * x", simplified("3 m x + 2 m x"));
+
+        // Sums that overflow are left as they are, too.
+        let int = |s: &str| {
+            let tree = parse_term(s, &Constants::<i64> { e: 2, pi: 3 }).unwrap();
+            simplify(&tree).to_string()
+        };
+        assert_eq!(
+            "x + 9223372036854775807 + 1",
+            int("x + 9223372036854775807 + 1")
+        );
+        assert_eq!(
+            "9223372036854775807 * x * 2",
+            int("x * 9223372036854775807 * 2")
+        );
+        assert_eq!(
+            "x^9223372036854775807 * x",
+            int("x^9223372036854775807 * x")
+        );
+        assert_eq!("x^2 * y", int("x * y * x"));
+        assert_eq!(
+            "-x * -9223372036854775808",
+            int("-x * (0-9223372036854775807-1)")
+        );
+    }
+
+    #[test]
+    fn test_same_value() {
+        let variables = Variables::from([("x".to_owned(), 3.0), ("y".to_owned(), -2.0)]);
+        for term in [
+            "(x + 2) * (x - 2) - 3 * x * y + y * x * 2",
+            "-(x - y) * -2 + 4! / x",
+            "x > y ? x - y - x : 2 * (y + y)",
+        ] {
+            let tree = parse_term(term, &CONSTANTS).unwrap();
+            let simplified = simplify(&tree);
+            assert_eq!(
+                calculate_with_variables(&tree, &variables),
+                calculate_with_variables(&simplified, &variables)
+            );
+            assert_eq!(simplified, simplify(&simplified));
+            assert!(simplified.len() <= tree.len());
+        }
+    }
+}
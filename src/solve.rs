@@ -0,0 +1,450 @@
+//! Equations and their roots.
+
+use std::{collections::BTreeSet, fmt};
+
+use crate::{
+    calculate::{CalculationError, Variables, calculate_subtree, variable_name},
+    derivative::diff,
+    polynomial::Polynomial,
+    token::{Function, Operator, Token},
+    traits::Numeric,
+    tree::{NodeId, Tree},
+};
+
+/// Two terms that are supposed to be equal, parsed from `left = right`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equation<T: Numeric> {
+    pub left: Tree<Token<T>>,
+    pub right: Tree<Token<T>>,
+}
+
+/// Where and how precisely roots are searched for numerically.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolveOptions {
+    /// Interval that is searched for sign changes and roots of even multiplicity.
+    pub range: (f64, f64),
+    /// Number of equal parts the range is sampled in.
+    pub samples: usize,
+    /// Roots are refined until they are this precise, relative to their size.
+    pub tolerance: f64,
+    pub max_iterations: usize,
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        SolveOptions {
+            range: (-100.0, 100.0),
+            samples: 2000,
+            tolerance: 1e-12,
+            max_iterations: 100,
+        }
+    }
+}
+
+impl<T: Numeric> Equation<T> {
+    /// The equation of a term whose root is `=`, `None` for any other term.
+    pub fn from_term(term: &Tree<Token<T>>) -> Option<Self> {
+        Self::at(term, term.root()?)
+    }
+
+    /// The equation at node `k` of `term` if there is one.
+    pub(crate) fn at(term: &Tree<Token<T>>, k: NodeId) -> Option<Self> {
+        match (term.get(k)?, term.children(k)) {
+            (Token::Op(Operator::EQ), &[left, right]) => Some(Equation {
+                left: term.subtree(left)?,
+                right: term.subtree(right)?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// `left - right`, which is zero exactly where the equation holds.
+    pub fn difference(&self) -> Tree<Token<T>> {
+        let mut tree = Tree::new();
+        let sides = [&self.left, &self.right].map(|side| match side.root() {
+            Some(root) => tree.graft(side, root),
+            None => tree.push(Token::Value(T::default()), vec![]),
+        });
+        let root = tree.push(Token::Op(Operator::SUB), sides.to_vec());
+        tree.set_root(root);
+        tree
+    }
+
+    /// Names of the variables on both sides in alphabetical order.
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        for side in [&self.left, &self.right] {
+            if let Some(root) = side.root() {
+                side.traverse_postorder(root, &mut |(_, token)| {
+                    if let Token::Var(name) = token {
+                        names.insert(name.clone());
+                    }
+                });
+            }
+        }
+        names
+    }
+
+    /// Roots for `var` in ascending order, see [`Equation::solve_with_variables`].
+    pub fn solve(&self, var: &str, options: &SolveOptions) -> Result<Vec<T>, CalculationError> {
+        self.solve_with_variables(var, &Variables::new(), options)
+    }
+
+    /// Roots for `var` in ascending order, other variables take their values from `variables`.
+    /// Polynomials up to degree two are solved exactly, everything else numerically within
+    /// [`SolveOptions::range`]. Integer types only get roots that are integers.
+    pub fn solve_with_variables(
+        &self,
+        var: &str,
+        variables: &Variables<T>,
+        options: &SolveOptions,
+    ) -> Result<Vec<T>, CalculationError> {
+        if let Some(name) = self
+            .variables()
+            .into_iter()
+            .find(|name| name != var && !variables.contains_key(name))
+        {
+            return Err(CalculationError::UnknownVariable(name));
+        }
+        let mut variables = variables.clone();
+        variables.remove(var);
+
+        let difference = self.difference();
+        let root = difference.root().unwrap_or_default();
+        let roots = match Polynomial::from_subtree(&difference, root, &variables)? {
+            Some(polynomial) if polynomial.is_zero() => {
+                return Err(CalculationError::Indeterminate(var.to_owned()));
+            }
+            Some(polynomial) if polynomial.degree(var) <= 2 => {
+                let coefficients: Vec<T> = polynomial
+                    .coefficients(var)
+                    .iter()
+                    .map(|c| c.as_constant().unwrap_or_default())
+                    .collect();
+                polynomial_roots(&coefficients)
+            }
+            _ => {
+                let mut function = Function1 {
+                    term: &difference,
+                    derivative: diff(&difference, var).ok(),
+                    var,
+                    variables,
+                };
+                function.roots(options)
+            }
+        };
+        Ok(roots.into_iter().filter_map(T::from_f64).collect())
+    }
+}
+
+impl<T: Numeric> fmt::Display for Equation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.left, self.right)
+    }
+}
+
+/// Real roots of a polynomial of degree one or two from its coefficients, lowest first.
+/// A constant has none.
+fn polynomial_roots<T: Numeric>(coefficients: &[T]) -> Vec<f64> {
+    match *coefficients {
+        [b, a] => vec![-b.to_f64() / a.to_f64()],
+        [c, b, a] => {
+            let (a, b, c) = (a.to_f64(), b.to_f64(), c.to_f64());
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return vec![];
+            }
+            if discriminant == 0.0 {
+                return vec![-b / (2.0 * a)];
+            }
+            // Avoids the cancellation of -b + sqrt(d) when both are close.
+            let q = -(b + b.signum() * discriminant.sqrt()) / 2.0;
+            let (x1, x2) = (q / a, c / q);
+            vec![x1.min(x2), x1.max(x2)]
+        }
+        _ => vec![],
+    }
+}
+
+/// A term in one variable evaluated as a real function.
+struct Function1<'a, T: Numeric> {
+    term: &'a Tree<Token<T>>,
+    derivative: Option<Tree<Token<T>>>,
+    var: &'a str,
+    variables: Variables<T>,
+}
+
+impl<T: Numeric> Function1<'_, T> {
+    /// Value of the term at `x`, `None` where it is undefined.
+    fn at(&mut self, x: f64) -> Option<f64> {
+        self.evaluate(x, false)
+    }
+
+    fn slope(&mut self, x: f64) -> Option<f64> {
+        self.evaluate(x, true)
+    }
+
+    fn evaluate(&mut self, x: f64, derivative: bool) -> Option<f64> {
+        let term = match derivative {
+            true => self.derivative.as_ref()?,
+            false => self.term,
+        };
+        self.variables.insert(self.var.to_owned(), T::from_f64(x)?);
+        let root = term.root()?;
+        let y = calculate_subtree(term, root, &self.variables)
+            .ok()?
+            .to_f64();
+        y.is_finite().then_some(y)
+    }
+
+    /// Samples the range and refines every sign change with [`Function1::bracketed`].
+    /// Local minima of `|f|` without a sign change start an unbracketed Newton iteration
+    /// to find roots of even multiplicity.
+    fn roots(&mut self, options: &SolveOptions) -> Vec<f64> {
+        let (start, end) = options.range;
+        let n = options.samples.max(1);
+        let xs: Vec<f64> = (0..=n)
+            .map(|i| start + (end - start) * i as f64 / n as f64)
+            .collect();
+        let ys: Vec<Option<f64>> = xs.iter().map(|&x| self.at(x)).collect();
+
+        let mut roots = vec![];
+        for i in 0..=n {
+            let Some(y) = ys[i] else { continue };
+            if y == 0.0 {
+                roots.push(xs[i]);
+                continue;
+            }
+            if let Some(Some(next)) = ys.get(i + 1)
+                && y.signum() != next.signum()
+                && *next != 0.0
+            {
+                roots.extend(self.bracketed((xs[i], y), (xs[i + 1], *next), options));
+            }
+            let neighbours = [i.checked_sub(1), Some(i + 1)].map(|j| j.and_then(|j| ys.get(j)));
+            let is_minimum = neighbours.iter().all(|neighbour| {
+                neighbour.is_some_and(|v| {
+                    v.is_some_and(|v| v.signum() == y.signum() && v.abs() >= y.abs())
+                })
+            });
+            if is_minimum {
+                roots.extend(self.newton(xs[i], (xs[i - 1], xs[i + 1]), options));
+            }
+        }
+
+        roots.sort_by(f64::total_cmp);
+        roots.dedup_by(|x, y| (*x - *y).abs() <= 1e3 * options.tolerance * (1.0 + y.abs()));
+        roots
+    }
+
+    /// Newton steps that stay inside the bracket of a sign change and bisection otherwise.
+    fn bracketed(
+        &mut self,
+        (mut a, fa): (f64, f64),
+        (mut b, fb): (f64, f64),
+        options: &SolveOptions,
+    ) -> Option<f64> {
+        let mut x = (a + b) / 2.0;
+        for _ in 0..options.max_iterations {
+            let fx = self.at(x)?;
+            if fx == 0.0 {
+                return Some(x);
+            }
+            match fx.signum() == fa.signum() {
+                true => a = x,
+                false => b = x,
+            }
+            let newton = self
+                .slope(x)
+                .map(|slope| x - fx / slope)
+                .filter(|next| a < *next && *next < b && (next - x).abs() < (b - a) / 2.0);
+            let next = newton.unwrap_or((a + b) / 2.0);
+            let converged = (next - x).abs() <= options.tolerance * (1.0 + x.abs());
+            x = next;
+            if converged || b - a <= options.tolerance * (1.0 + x.abs()) {
+                break;
+            }
+        }
+        // Near a pole the sign changes as well, but the function grows instead of vanishing.
+        let fx = self.at(x)?;
+        (fx.abs() <= fa.abs().min(fb.abs())).then_some(x)
+    }
+
+    /// Newton iteration from `x` that has to stay within `bounds` and end close to zero.
+    fn newton(&mut self, mut x: f64, bounds: (f64, f64), options: &SolveOptions) -> Option<f64> {
+        for _ in 0..options.max_iterations {
+            let fx = self.at(x)?;
+            if fx == 0.0 {
+                return Some(x);
+            }
+            let next = x - fx / self.slope(x)?;
+            if !(bounds.0..=bounds.1).contains(&next) {
+                return None;
+            }
+            let converged = (next - x).abs() <= options.tolerance * (1.0 + x.abs());
+            x = next;
+            if converged {
+                break;
+            }
+        }
+        (self.at(x)?.abs() <= options.tolerance.sqrt()).then_some(x)
+    }
+}
+
+/// Roots of the term if it is an equation or a call to `solve`, `None` for any other term.
+/// A bare equation has to contain a single variable, which is returned with the roots.
+pub fn solve_result<T: Numeric>(
+    parsed_term: &Tree<Token<T>>,
+    options: &SolveOptions,
+) -> Result<Option<(String, Vec<T>)>, CalculationError> {
+    let Some(root) = parsed_term.root() else {
+        return Ok(None);
+    };
+    let (equation, var) = match (parsed_term.get(root), parsed_term.children(root)) {
+        (Some(Token::Func(Function::SOLVE)), &[equation, var]) => {
+            let var = variable_name(parsed_term, var, Function::SOLVE)?;
+            let equation = Equation::at(parsed_term, equation)
+                .ok_or(CalculationError::ExpectedEquation(Function::SOLVE))?;
+            (equation, var.to_owned())
+        }
+        (Some(Token::Op(Operator::EQ)), _) => {
+            let Some(equation) = Equation::from_term(parsed_term) else {
+                return Ok(None);
+            };
+            let variables = equation.variables();
+            match variables.len() {
+                1 => {
+                    let var = variables.into_iter().next().unwrap_or_default();
+                    (equation, var)
+                }
+                n => return Err(CalculationError::AmbiguousEquation(n)),
+            }
+        }
+        _ => return Ok(None),
+    };
+    let roots = equation.solve(&var, options)?;
+    Ok(Some((var, roots)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_term, traits::Constants};
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    fn roots(s: &str) -> Vec<f64> {
+        let equation = Equation::from_term(&parse_term(s, &CONSTANTS).unwrap()).unwrap();
+        equation.solve("x", &SolveOptions::default()).unwrap()
+    }
+
+    fn assert_roots(expected: &[f64], s: &str) {
+        let found = roots(s);
+        assert_eq!(expected.len(), found.len(), "{s}: {found:?}");
+        for (e, f) in expected.iter().zip(&found) {
+            assert!((e - f).abs() < 1e-9, "{s}: {found:?}");
+        }
+    }
+
+    #[test]
+    fn test_equation() {
+        let term = parse_term("2x + 3 = 11 * y", &CONSTANTS).unwrap();
+        let equation = Equation::from_term(&term).unwrap();
+        assert_eq!("2 * x + 3 = 11 * y", equation.to_string());
+        assert_eq!("2 * x + 3 - 11 * y", equation.difference().to_string());
+        assert_eq!(
+            vec!["x", "y"],
+            equation.variables().into_iter().collect::<Vec<_>>()
+        );
+        assert!(Equation::from_term(&parse_term("2x + 3", &CONSTANTS).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_polynomials() {
+        assert_eq!(vec![4.0], roots("2x + 3 = 11"));
+        assert_eq!(vec![-3.0, 1.0], roots("x^2 + 2x = 3"));
+        assert_eq!(vec![2.0], roots("(x - 2)^2 = 0"));
+        assert_eq!(Vec::<f64>::new(), roots("x^2 = -1"));
+        assert_eq!(Vec::<f64>::new(), roots("x + 1 = x"));
+        assert_roots(&[-2f64.sqrt(), 2f64.sqrt()], "x * x / 2 = 1");
+        // The small root of a quadratic loses no precision to cancellation.
+        assert!((roots("x^2 + 1e8 x + 1 = 0")[1] + 1e-8).abs() < 1e-20);
+        assert_roots(&[-1.0, 0.0, 1.0], "x^3 = x");
+
+        let term = parse_term("2 * (x + 1) = 2x + 2", &CONSTANTS).unwrap();
+        let equation = Equation::from_term(&term).unwrap();
+        assert_eq!(
+            Err(CalculationError::Indeterminate("x".to_owned())),
+            equation.solve("x", &SolveOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_numeric() {
+        let pi = std::f64::consts::PI;
+        assert_roots(&[0.739_085_133_215_160_6], "cos(x) = x");
+        assert_roots(&[1.0], "ln(x) = 0");
+        assert_roots(&[2f64.ln()], "exp(x) = 2");
+        // Sign changes at a pole aren't roots.
+        assert_roots(&[], "1 / (x - 0.05) = 0");
+        // Roots of even multiplicity don't change sign and are less precise.
+        let found = roots("(x - 0.55)^2 * exp(x) = 0");
+        assert!(
+            found.len() == 1 && (found[0] - 0.55).abs() < 1e-6,
+            "{found:?}"
+        );
+        assert_roots(&[-2.0, -1.0, 1.0, 2.0], "x^4 - 5x^2 + 4 = 0");
+
+        let options = SolveOptions {
+            range: (0.0, 10.0),
+            ..SolveOptions::default()
+        };
+        let term = parse_term("sin(x) = 0", &CONSTANTS).unwrap();
+        let found = Equation::from_term(&term)
+            .unwrap()
+            .solve("x", &options)
+            .unwrap();
+        assert_eq!(4, found.len());
+        assert!((found[3] - 3.0 * pi).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_solve_result() {
+        let options = SolveOptions::default();
+        let solve = |s: &str| solve_result(&parse_term(s, &CONSTANTS).unwrap(), &options);
+
+        assert_eq!(
+            Ok(Some(("y".to_owned(), vec![-2.0, 2.0]))),
+            solve("y^2 = 4")
+        );
+        assert_eq!(
+            Ok(Some(("x".to_owned(), vec![0.0, 2.0]))),
+            solve("solve(x^2 = 2x, x)")
+        );
+        assert_eq!(
+            Err(CalculationError::UnknownVariable("b".to_owned())),
+            solve("solve(a - b = 0, a)")
+        );
+        assert_eq!(Ok(None), solve("2 + 2"));
+        assert_eq!(Err(CalculationError::AmbiguousEquation(2)), solve("x = y"));
+        assert_eq!(Err(CalculationError::AmbiguousEquation(0)), solve("1 = 2"));
+        assert_eq!(
+            Err(CalculationError::ExpectedEquation(Function::SOLVE)),
+            solve("solve(x, x)")
+        );
+        assert_eq!(
+            Err(CalculationError::ExpectedVariable(Function::SOLVE)),
+            solve("solve(x = 1, 2)")
+        );
+
+        let int_constants = Constants::<i64> { e: 3, pi: 3 };
+        let term = parse_term("solve(3x - 1 = 2x^2, x)", &int_constants).unwrap();
+        assert_eq!(
+            Ok(Some(("x".to_owned(), vec![1]))),
+            solve_result(&term, &options)
+        );
+    }
+}
@@ -7,14 +7,88 @@ use anyhow::anyhow;
 
 use crate::traits::{FromStrError, Numeric};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Operator {
+    /// Separates the sides of an equation, or ends the input as its last token
     EQ,
     IN,
     OUT,
     ADD,
     MUL,
     SUB,
+    DIV,
+    IDIV,
+    MOD,
+    REM,
+    /// `^`, exponentiation
+    POW,
+    /// Prefix minus
+    NEG,
+    /// Postfix factorial
+    FAC,
+    /// Separates function arguments
+    SEP,
+    LT,
+    LE,
+    GT,
+    GE,
+    /// `==`, comparison for equality
+    IS,
+    NE,
+    AND,
+    OR,
+    /// Prefix logical not
+    NOT,
+    /// `?` of the ternary conditional
+    COND,
+    /// `:` of the ternary conditional
+    ELSE,
+    /// `to` or `in`, converts the left operand into the unit on the right
+    TO,
+}
+
+impl Operator {
+    /// Binding strength of operators, higher binds tighter.
+    /// Parentheses and `,` never bind, `=` binds weakest of all, followed by `to`.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::EQ | Operator::IN | Operator::OUT | Operator::SEP => 0,
+            Operator::TO => 1,
+            Operator::COND | Operator::ELSE => 2,
+            Operator::OR => 3,
+            Operator::AND => 4,
+            Operator::IS | Operator::NE => 5,
+            Operator::LT | Operator::LE | Operator::GT | Operator::GE => 6,
+            Operator::ADD | Operator::SUB => 7,
+            Operator::MUL | Operator::DIV | Operator::IDIV | Operator::MOD | Operator::REM => 8,
+            Operator::NEG | Operator::NOT => 9,
+            Operator::FAC => 10,
+            Operator::POW => 11,
+        }
+    }
+
+    /// The ternary conditional and `^` group to the right, all other binary operators to the left.
+    pub fn is_left_associative(&self) -> bool {
+        !matches!(self, Operator::COND | Operator::ELSE | Operator::POW)
+    }
+
+    /// Number of operands, zero for the structural operators.
+    /// The conditional takes the condition and both branches, `:` only separates them.
+    /// An equation has its two sides.
+    pub fn arity(&self) -> usize {
+        match self {
+            Operator::IN | Operator::OUT | Operator::SEP | Operator::ELSE => 0,
+            Operator::NEG | Operator::NOT | Operator::FAC => 1,
+            Operator::COND => 3,
+            _ => 2,
+        }
+    }
 }
 
 impl fmt::Display for Operator {
@@ -26,19 +100,126 @@ impl fmt::Display for Operator {
             Operator::ADD => "+",
             Operator::MUL => "*",
             Operator::SUB => "-",
+            Operator::DIV => "/",
+            Operator::IDIV => "//",
+            Operator::MOD => "mod",
+            Operator::REM => "rem",
+            Operator::POW => "^",
+            Operator::NEG => "-",
+            Operator::FAC => "!",
+            Operator::SEP => ",",
+            Operator::LT => "<",
+            Operator::LE => "<=",
+            Operator::GT => ">",
+            Operator::GE => ">=",
+            Operator::IS => "==",
+            Operator::NE => "!=",
+            Operator::AND => "&&",
+            Operator::OR => "||",
+            Operator::NOT => "!",
+            Operator::COND => "?",
+            Operator::ELSE => ":",
+            Operator::TO => "to",
         };
         f.write_str(symbol)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Function {
+    NCR,
+    NPR,
+    GCD,
+    LCM,
+    ISPRIME,
+    FACTOR,
+    SIN,
+    COS,
+    TAN,
+    EXP,
+    LN,
+    SQRT,
+    /// Derivative of the first argument with respect to the variable in the second
+    DIFF,
+    /// Definite integral of the first argument over the variable in the second,
+    /// from the third to the fourth argument
+    INTEGRATE,
+    /// Roots of the equation in the first argument for the variable in the second
+    SOLVE,
+    /// Multiplied out form of a polynomial
+    EXPAND,
+}
+
+impl Function {
+    pub fn arity(&self) -> usize {
+        match self {
+            Function::ISPRIME
+            | Function::FACTOR
+            | Function::SIN
+            | Function::COS
+            | Function::TAN
+            | Function::EXP
+            | Function::LN
+            | Function::SQRT
+            | Function::EXPAND => 1,
+            Function::NCR
+            | Function::NPR
+            | Function::GCD
+            | Function::LCM
+            | Function::DIFF
+            | Function::SOLVE => 2,
+            Function::INTEGRATE => 4,
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Function::NCR => "nCr",
+            Function::NPR => "nPr",
+            Function::GCD => "gcd",
+            Function::LCM => "lcm",
+            Function::ISPRIME => "isprime",
+            Function::FACTOR => "factor",
+            Function::SIN => "sin",
+            Function::COS => "cos",
+            Function::TAN => "tan",
+            Function::EXP => "exp",
+            Function::LN => "ln",
+            Function::SQRT => "sqrt",
+            Function::DIFF => "diff",
+            Function::INTEGRATE => "integrate",
+            Function::SOLVE => "solve",
+            Function::EXPAND => "expand",
+        };
+        f.write_str(name)
+    }
+}
+
+/// With the `serde` feature a token is stored as `{"kind": "op", "value": "add"}`,
+/// `{"kind": "value", "value": 2.5}` or `{"kind": "empty"}`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", content = "value", rename_all = "lowercase")
+)]
 pub enum Token<T>
 where
     T: Numeric,
 {
     Empty,
     Op(Operator),
+    Func(Function),
     Value(T),
+    Var(String),
 }
 
 impl<T> PartialEq<Operator> for Token<T>
@@ -62,12 +243,30 @@ impl<T: Numeric> fmt::Display for Token<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Token::Op(op) => write!(f, "{}", op),
-            Token::Value(v) => write!(f, "{}", v.to_string()),
+            Token::Func(func) => write!(f, "{}", func),
+            Token::Var(name) => write!(f, "{}", name),
+            Token::Value(v) => write!(f, "{}", v),
             Token::Empty => write!(f, "empty"),
         }
     }
 }
 
+/// Decimal literal with an exponent, `6.022e23`. A lone `e` is Euler's number instead.
+fn is_scientific(el: &str) -> bool {
+    el.starts_with(|c: char| c.is_ascii_digit()) && el.contains(['e', 'E'])
+}
+
+/// Numbers become values, any other word names a variable.
+fn parse_value_or_var<T: Numeric>(el: &str) -> Result<Token<T>, FromStrError> {
+    match el.parse::<T>() {
+        Ok(n) => Ok(Token::Value(n)),
+        Err(_) if !el.is_empty() && el.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Ok(Token::Var(el.to_owned()))
+        }
+        Err(_) => Err(anyhow!("parsing {} failed", el).into()),
+    }
+}
+
 impl<T: Numeric> FromStr for Token<T> {
     type Err = FromStrError;
 
@@ -76,25 +275,51 @@ impl<T: Numeric> FromStr for Token<T> {
             "*" => Token::<T>::mul(),
             "+" => Token::<T>::add(),
             "-" => Token::<T>::sub(),
+            "/" => Token::Op(Operator::DIV),
+            "//" => Token::Op(Operator::IDIV),
+            "mod" => Token::Op(Operator::MOD),
+            "rem" => Token::Op(Operator::REM),
+            "^" => Token::Op(Operator::POW),
+            "!" => Token::Op(Operator::FAC),
+            "," => Token::Op(Operator::SEP),
+            "<" => Token::Op(Operator::LT),
+            "<=" => Token::Op(Operator::LE),
+            ">" => Token::Op(Operator::GT),
+            ">=" => Token::Op(Operator::GE),
+            "==" => Token::Op(Operator::IS),
+            "!=" => Token::Op(Operator::NE),
+            "&&" => Token::Op(Operator::AND),
+            "||" => Token::Op(Operator::OR),
+            "?" => Token::Op(Operator::COND),
+            ":" => Token::Op(Operator::ELSE),
+            "to" | "in" => Token::Op(Operator::TO),
+            "ncr" => Token::Func(Function::NCR),
+            "npr" => Token::Func(Function::NPR),
+            "gcd" => Token::Func(Function::GCD),
+            "lcm" => Token::Func(Function::LCM),
+            "isprime" => Token::Func(Function::ISPRIME),
+            "factor" => Token::Func(Function::FACTOR),
+            "sin" => Token::Func(Function::SIN),
+            "cos" => Token::Func(Function::COS),
+            "tan" => Token::Func(Function::TAN),
+            "exp" => Token::Func(Function::EXP),
+            "ln" => Token::Func(Function::LN),
+            "sqrt" => Token::Func(Function::SQRT),
+            "diff" => Token::Func(Function::DIFF),
+            "integrate" => Token::Func(Function::INTEGRATE),
+            "solve" => Token::Func(Function::SOLVE),
+            "expand" => Token::Func(Function::EXPAND),
             "(" => Token::<T>::i(),
             ")" => Token::<T>::out(),
             "=" => Token::<T>::eq(),
-            el => {
-                if el.len() >= 2 {
-                    match &el[..2] {
-                        "0x" => T::from_hex_str(el).map(|n| Token::<T>::Value(n))?,
-                        "0b" => T::from_binary_str(el).map(|n| Token::<T>::Value(n))?,
-                        _ => el
-                            .parse::<T>()
-                            .map(|n| Token::<T>::Value(n))
-                            .map_err(|_| anyhow!("parsing {} failed", el))?,
-                    }
-                } else {
-                    el.parse::<T>()
-                        .map(|n| Token::<T>::Value(n))
-                        .map_err(|_| anyhow!("parsing {} failed", el))?
+            el => match el.get(..2) {
+                Some("0x") => T::from_hex_str(el).map(|n| Token::<T>::Value(n))?,
+                Some("0b") => T::from_binary_str(el).map(|n| Token::<T>::Value(n))?,
+                _ if is_scientific(el) => {
+                    T::from_scientific_str(el).map(|n| Token::<T>::Value(n))?
                 }
-            }
+                _ => parse_value_or_var(el)?,
+            },
         };
 
         Ok(token)
@@ -121,6 +346,15 @@ impl<T: Numeric> Token<T> {
         Self::Op(Operator::SUB)
     }
 
+    /// Number of children the token takes in a parsed tree.
+    pub fn arity(&self) -> usize {
+        match self {
+            Token::Op(op) => op.arity(),
+            Token::Func(func) => func.arity(),
+            Token::Value(_) | Token::Var(_) | Token::Empty => 0,
+        }
+    }
+
     pub fn is_add(&self) -> bool {
         self == Operator::ADD
     }
@@ -5,8 +5,11 @@ use std::{
     str::FromStr,
 };
 
+use anyhow::anyhow;
 use thiserror::Error;
 
+use crate::{number_theory, token::Operator};
+
 pub trait One
 where
     Self: Sized,
@@ -20,9 +23,34 @@ where
     }
 }
 
-impl One for f32 {
-    fn one() -> Self {
-        1.0
+macro_rules! impl_one {
+    ($($t:ty => $one:expr),*) => {$(
+        impl One for $t {
+            fn one() -> Self {
+                $one
+            }
+        }
+    )*};
+}
+
+impl_one!(f32 => 1.0, f64 => 1.0, i32 => 1, i64 => 1);
+
+/// Truth values are represented by the numeric type itself,
+/// one is true and zero is false. Any non-zero value counts as true.
+pub trait Truth {
+    fn from_bool(b: bool) -> Self;
+    fn is_true(&self) -> bool;
+}
+
+impl<T> Truth for T
+where
+    T: One + Default + PartialEq,
+{
+    fn from_bool(b: bool) -> Self {
+        if b { T::one() } else { T::default() }
+    }
+    fn is_true(&self) -> bool {
+        *self != T::default()
     }
 }
 
@@ -116,6 +144,568 @@ impl FromHexStr for f32 {
         }
     }
 }
+impl FromBinaryStr for f64 {
+    fn from_binary_str(s: &str) -> Result<Self, FromStrError> {
+        let (num_part, suffix) = split_at_first_non_digit(s.trim_start_matches("0b"));
+        match suffix {
+            "" => {
+                let bits = u64::from_str_radix(num_part, 2)?;
+                Ok(f64::from_bits(bits))
+            }
+            _ => Ok(int_from_radix_str(num_part, suffix, 2)? as f64),
+        }
+    }
+}
+
+impl FromHexStr for f64 {
+    fn from_hex_str(s: &str) -> Result<Self, FromStrError> {
+        let (num_part, suffix) = split_at_first_non_digit(s.trim_start_matches("0x"));
+        match suffix {
+            "" => {
+                let bits = u64::from_str_radix(num_part, 16)?;
+                Ok(f64::from_bits(bits))
+            }
+            _ => Ok(int_from_radix_str(num_part, suffix, 16)? as f64),
+        }
+    }
+}
+
+/// Parses the digits of a binary or hex literal honoring the optional width suffix.
+/// Without a suffix the digits are read as a 64 bit pattern.
+fn int_from_radix_str(num_part: &str, suffix: &str, radix: u32) -> Result<i64, FromStrError> {
+    match suffix {
+        "i16" => Ok((u16::from_str_radix(num_part, radix)? as i16) as i64),
+        "u16" => Ok(u16::from_str_radix(num_part, radix)? as i64),
+        "u8" => Ok(u8::from_str_radix(num_part, radix)? as i64),
+        "i8" => Ok((u8::from_str_radix(num_part, radix)? as i8) as i64),
+        "" => Ok(u64::from_str_radix(num_part, radix)? as i64),
+        other => Err(FromStrError::InvalidSuffix(other.to_owned())),
+    }
+}
+
+macro_rules! impl_radix_str_for_int {
+    ($($t:ty),*) => {$(
+        impl FromBinaryStr for $t {
+            fn from_binary_str(s: &str) -> Result<Self, FromStrError> {
+                let (num_part, suffix) = split_at_first_non_digit(s.trim_start_matches("0b"));
+                let value = int_from_radix_str(num_part, suffix, 2)?;
+                <$t>::try_from(value).map_err(|_| anyhow!("{} does not fit into {}", s, stringify!($t)).into())
+            }
+        }
+
+        impl FromHexStr for $t {
+            fn from_hex_str(s: &str) -> Result<Self, FromStrError> {
+                let (num_part, suffix) = split_at_first_non_digit(s.trim_start_matches("0x"));
+                let value = int_from_radix_str(num_part, suffix, 16)?;
+                <$t>::try_from(value).map_err(|_| anyhow!("{} does not fit into {}", s, stringify!($t)).into())
+            }
+        }
+    )*};
+}
+
+impl_radix_str_for_int!(i32, i64);
+
+/// Parses decimal literals with an exponent like `6.022e23` or `1.5e-3`.
+pub trait FromScientificStr: Sized {
+    fn from_scientific_str(s: &str) -> Result<Self, FromStrError>;
+}
+
+macro_rules! impl_scientific_str_for_float {
+    ($($t:ty),*) => {$(
+        impl FromScientificStr for $t {
+            fn from_scientific_str(s: &str) -> Result<Self, FromStrError> {
+                // The standard parser rounds correctly from the full decimal representation.
+                Ok(s.parse::<$t>()?)
+            }
+        }
+    )*};
+}
+
+/// Reads the literal exactly by shifting its digits, fails if the value isn't an integer.
+fn int_from_scientific_str(s: &str) -> Result<i128, FromStrError> {
+    let (mantissa, exponent) = s.split_once(['e', 'E']).unwrap_or((s, "0"));
+    let exponent: i64 = exponent.parse()?;
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let value: i128 = format!("{int_part}{frac_part}").parse()?;
+
+    let shift = exponent - frac_part.len() as i64;
+    let scale = u32::try_from(shift.unsigned_abs())
+        .ok()
+        .and_then(|shift| 10i128.checked_pow(shift));
+    match scale {
+        _ if value == 0 => Ok(0),
+        Some(scale) if shift >= 0 => value
+            .checked_mul(scale)
+            .ok_or_else(|| anyhow!("{} is too large", s).into()),
+        Some(scale) if value % scale == 0 => Ok(value / scale),
+        _ => Err(anyhow!("{} is not an integer", s).into()),
+    }
+}
+
+macro_rules! impl_scientific_str_for_int {
+    ($($t:ty),*) => {$(
+        impl FromScientificStr for $t {
+            fn from_scientific_str(s: &str) -> Result<Self, FromStrError> {
+                let value = int_from_scientific_str(s)?;
+                <$t>::try_from(value).map_err(|_| anyhow!("{} does not fit into {}", s, stringify!($t)).into())
+            }
+        }
+    )*};
+}
+
+impl_scientific_str_for_float!(f32, f64);
+impl_scientific_str_for_int!(i32, i64);
+
+/// Parses dates, times of day and ISO 8601 durations like `2026-10-18`, `17:45` or `PT8H15M`.
+/// Types without such values leave them to the tokenizer, which reads `2000-12-31` as a difference.
+pub trait FromTemporalStr: Sized {
+    /// `None` if the type has no dates, times and durations.
+    fn from_temporal_str(_s: &str) -> Option<Result<Self, FromStrError>> {
+        None
+    }
+}
+
+impl FromTemporalStr for f32 {}
+impl FromTemporalStr for f64 {}
+impl FromTemporalStr for i32 {}
+impl FromTemporalStr for i64 {}
+
+/// Division flavours beyond the plain `Div` operator.
+///
+/// All methods return `None` if the result is undefined, i.e. the divisor is zero
+/// or the quotient overflows the integer type.
+///
+/// Sign semantics for negative operands:
+/// - `quotient`: `/`, truncates towards zero for integers, exact for floats
+/// - `floor_div`: `//`, rounds the quotient towards negative infinity (`-7 // 2 == -4`)
+/// - `modulo`: `mod`, Euclidean remainder, always in `[0, |rhs|)` (`-7 mod 2 == 1`)
+/// - `remainder`: `rem`, truncating remainder, takes the sign of `self` (`-7 rem 2 == -1`)
+pub trait Division: Sized {
+    fn quotient(self, rhs: Self) -> Option<Self>;
+    fn floor_div(self, rhs: Self) -> Option<Self>;
+    fn modulo(self, rhs: Self) -> Option<Self>;
+    fn remainder(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_division_for_float {
+    ($($t:ty),*) => {$(
+        impl Division for $t {
+            fn quotient(self, rhs: Self) -> Option<Self> {
+                (rhs != 0.0).then(|| self / rhs)
+            }
+            fn floor_div(self, rhs: Self) -> Option<Self> {
+                (rhs != 0.0).then(|| (self / rhs).floor())
+            }
+            fn modulo(self, rhs: Self) -> Option<Self> {
+                (rhs != 0.0).then(|| self.rem_euclid(rhs))
+            }
+            fn remainder(self, rhs: Self) -> Option<Self> {
+                (rhs != 0.0).then(|| self % rhs)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_division_for_int {
+    ($($t:ty),*) => {$(
+        impl Division for $t {
+            fn quotient(self, rhs: Self) -> Option<Self> {
+                self.checked_div(rhs)
+            }
+            fn floor_div(self, rhs: Self) -> Option<Self> {
+                let q = self.checked_div(rhs)?;
+                if self % rhs != 0 && ((self < 0) != (rhs < 0)) {
+                    Some(q - 1)
+                } else {
+                    Some(q)
+                }
+            }
+            fn modulo(self, rhs: Self) -> Option<Self> {
+                self.checked_rem_euclid(rhs)
+            }
+            fn remainder(self, rhs: Self) -> Option<Self> {
+                self.checked_rem(rhs)
+            }
+        }
+    )*};
+}
+
+impl_division_for_float!(f32, f64);
+impl_division_for_int!(i32, i64);
+
+/// `+`, `-`, `*` and negation that return `None` instead of overflowing.
+/// Only integers overflow, the other types keep the results of their operators.
+pub trait Checked:
+    Sized + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+{
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        Some(self - rhs)
+    }
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+    fn checked_neg(self) -> Option<Self> {
+        Some(-self)
+    }
+}
+
+impl Checked for f32 {}
+impl Checked for f64 {}
+
+macro_rules! impl_checked_for_int {
+    ($($t:ty),*) => {$(
+        impl Checked for $t {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+            fn checked_neg(self) -> Option<Self> {
+                <$t>::checked_neg(self)
+            }
+        }
+    )*};
+}
+
+impl_checked_for_int!(i32, i64);
+
+/// Factorial, combinatorics and number theory.
+///
+/// Integer types compute exact results and return `None` on overflow or for
+/// negative arguments. Float types extend factorial, `nPr` and `nCr` to
+/// non-integers via the gamma function, the divisibility based methods
+/// require integral values and return `None` otherwise.
+pub trait Combinatorics: Sized {
+    fn factorial(self) -> Option<Self>;
+    fn permutations(self, k: Self) -> Option<Self>;
+    fn combinations(self, k: Self) -> Option<Self>;
+    fn gcd(self, other: Self) -> Option<Self>;
+    fn lcm(self, other: Self) -> Option<Self>;
+    fn is_prime(&self) -> Option<bool>;
+    /// Prime factors in ascending order, negative numbers start with a factor of -1.
+    fn prime_factors(self) -> Option<Vec<Self>>;
+}
+
+/// Largest integer up to which every integer is exactly representable in an f64.
+const MAX_EXACT_F64: f64 = 9_007_199_254_740_992.0;
+
+fn f64_to_u64(x: f64) -> Option<u64> {
+    (x.fract() == 0.0 && (0.0..=MAX_EXACT_F64).contains(&x)).then_some(x as u64)
+}
+
+fn finite(x: f64) -> Option<f64> {
+    x.is_finite().then_some(x)
+}
+
+fn float_factorial(x: f64) -> Option<f64> {
+    match f64_to_u64(x) {
+        Some(n) if n <= 170 => Some((2..=n).fold(1.0, |acc, i| acc * i as f64)),
+        _ if x < 0.0 && x.fract() == 0.0 => None,
+        _ => finite(number_theory::gamma(x + 1.0)),
+    }
+}
+
+fn float_permutations(n: f64, k: f64) -> Option<f64> {
+    match (f64_to_u64(n), f64_to_u64(k)) {
+        (Some(n), Some(k)) if k > n => Some(0.0),
+        (Some(n), Some(k)) => Some((n - k + 1..=n).fold(1.0, |acc, i| acc * i as f64)),
+        _ => finite(float_factorial(n)? / float_factorial(n - k)?),
+    }
+}
+
+fn float_combinations(n: f64, k: f64) -> Option<f64> {
+    match (f64_to_u64(n), f64_to_u64(k)) {
+        (Some(n), Some(k)) if k > n => Some(0.0),
+        (Some(n), Some(k)) => {
+            let k = k.min(n - k);
+            let c = (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64);
+            Some(c.round())
+        }
+        _ => finite(float_factorial(n)? / (float_factorial(k)? * float_factorial(n - k)?)),
+    }
+}
+
+fn float_gcd(a: f64, b: f64) -> Option<f64> {
+    Some(number_theory::gcd(f64_to_u64(a.abs())?, f64_to_u64(b.abs())?) as f64)
+}
+
+fn float_lcm(a: f64, b: f64) -> Option<f64> {
+    let g = float_gcd(a, b)?;
+    if g == 0.0 {
+        return Some(0.0);
+    }
+    finite((a / g * b).abs())
+}
+
+fn float_prime_factors(x: f64) -> Option<Vec<f64>> {
+    let n = f64_to_u64(x.abs())?;
+    if n == 0 {
+        return None;
+    }
+    let sign = (x < 0.0).then_some(-1.0);
+    let factors = number_theory::prime_factors(n)
+        .into_iter()
+        .map(|p| p as f64);
+    Some(sign.into_iter().chain(factors).collect())
+}
+
+macro_rules! impl_combinatorics_for_float {
+    ($($t:ty),*) => {$(
+        impl Combinatorics for $t {
+            fn factorial(self) -> Option<Self> {
+                float_factorial(self as f64).map(|x| x as $t)
+            }
+            fn permutations(self, k: Self) -> Option<Self> {
+                float_permutations(self as f64, k as f64).map(|x| x as $t)
+            }
+            fn combinations(self, k: Self) -> Option<Self> {
+                float_combinations(self as f64, k as f64).map(|x| x as $t)
+            }
+            fn gcd(self, other: Self) -> Option<Self> {
+                float_gcd(self as f64, other as f64).map(|x| x as $t)
+            }
+            fn lcm(self, other: Self) -> Option<Self> {
+                float_lcm(self as f64, other as f64).map(|x| x as $t)
+            }
+            fn is_prime(&self) -> Option<bool> {
+                if self.fract() != 0.0 || *self < 2.0 {
+                    return Some(false);
+                }
+                f64_to_u64(*self as f64).map(number_theory::is_prime)
+            }
+            fn prime_factors(self) -> Option<Vec<Self>> {
+                float_prime_factors(self as f64).map(|v| v.into_iter().map(|x| x as $t).collect())
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_combinatorics_for_int {
+    ($($t:ty),*) => {$(
+        impl Combinatorics for $t {
+            fn factorial(self) -> Option<Self> {
+                let n = u64::try_from(self).ok()?;
+                <$t>::try_from(number_theory::factorial(n)?).ok()
+            }
+            fn permutations(self, k: Self) -> Option<Self> {
+                let (n, k) = (u64::try_from(self).ok()?, u64::try_from(k).ok()?);
+                <$t>::try_from(number_theory::permutations(n, k)?).ok()
+            }
+            fn combinations(self, k: Self) -> Option<Self> {
+                let (n, k) = (u64::try_from(self).ok()?, u64::try_from(k).ok()?);
+                <$t>::try_from(number_theory::combinations(n, k)?).ok()
+            }
+            fn gcd(self, other: Self) -> Option<Self> {
+                let g = number_theory::gcd(self.unsigned_abs() as u64, other.unsigned_abs() as u64);
+                <$t>::try_from(g).ok()
+            }
+            fn lcm(self, other: Self) -> Option<Self> {
+                let g = self.gcd(other)?;
+                if g == 0 {
+                    return Some(0);
+                }
+                (self / g).checked_mul(other).and_then(|x| x.checked_abs())
+            }
+            fn is_prime(&self) -> Option<bool> {
+                Some(u64::try_from(*self).is_ok_and(number_theory::is_prime))
+            }
+            fn prime_factors(self) -> Option<Vec<Self>> {
+                if self == 0 {
+                    return None;
+                }
+                let sign = (self < 0).then_some(-1);
+                let factors = number_theory::prime_factors(self.unsigned_abs() as u64)
+                    .into_iter()
+                    .map(|p| p as $t);
+                Some(sign.into_iter().chain(factors).collect())
+            }
+        }
+    )*};
+}
+
+impl_combinatorics_for_float!(f32, f64);
+impl_combinatorics_for_int!(i32, i64);
+
+/// Powers and the elementary functions. Float types return `None` where the
+/// result is not a finite real number, integer types only return results that
+/// are exact integers, like `exponential(0)` or `root(49)`.
+pub trait Elementary: Sized {
+    fn power(self, exponent: Self) -> Option<Self>;
+    /// Square root
+    fn root(self) -> Option<Self>;
+    fn sine(self) -> Option<Self>;
+    fn cosine(self) -> Option<Self>;
+    fn tangent(self) -> Option<Self>;
+    fn exponential(self) -> Option<Self>;
+    /// Natural logarithm
+    fn logarithm(self) -> Option<Self>;
+}
+
+fn f64_to_i64(x: f64) -> Option<i64> {
+    (x.fract() == 0.0 && x.abs() <= MAX_EXACT_F64).then_some(x as i64)
+}
+
+macro_rules! impl_elementary_for_float {
+    ($($t:ty),*) => {$(
+        impl Elementary for $t {
+            fn power(self, exponent: Self) -> Option<Self> {
+                finite((self as f64).powf(exponent as f64)).map(|x| x as $t)
+            }
+            fn root(self) -> Option<Self> {
+                finite((self as f64).sqrt()).map(|x| x as $t)
+            }
+            fn sine(self) -> Option<Self> {
+                finite((self as f64).sin()).map(|x| x as $t)
+            }
+            fn cosine(self) -> Option<Self> {
+                finite((self as f64).cos()).map(|x| x as $t)
+            }
+            fn tangent(self) -> Option<Self> {
+                finite((self as f64).tan()).map(|x| x as $t)
+            }
+            fn exponential(self) -> Option<Self> {
+                finite((self as f64).exp()).map(|x| x as $t)
+            }
+            fn logarithm(self) -> Option<Self> {
+                finite((self as f64).ln()).map(|x| x as $t)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_elementary_for_int {
+    ($($t:ty),*) => {$(
+        impl Elementary for $t {
+            fn power(self, exponent: Self) -> Option<Self> {
+                match u32::try_from(exponent) {
+                    Ok(exponent) => self.checked_pow(exponent),
+                    // Only one and minus one have integral reciprocals.
+                    Err(_) if matches!(self, 1 | -1) => Some(if exponent % 2 == 0 { 1 } else { self }),
+                    Err(_) => None,
+                }
+            }
+            fn root(self) -> Option<Self> {
+                let r = (self as f64).sqrt().round() as $t;
+                (self >= 0 && r.checked_mul(r) == Some(self)).then_some(r)
+            }
+            fn sine(self) -> Option<Self> {
+                <$t>::try_from(f64_to_i64((self as f64).sin())?).ok()
+            }
+            fn cosine(self) -> Option<Self> {
+                <$t>::try_from(f64_to_i64((self as f64).cos())?).ok()
+            }
+            fn tangent(self) -> Option<Self> {
+                <$t>::try_from(f64_to_i64((self as f64).tan())?).ok()
+            }
+            fn exponential(self) -> Option<Self> {
+                <$t>::try_from(f64_to_i64((self as f64).exp())?).ok()
+            }
+            fn logarithm(self) -> Option<Self> {
+                <$t>::try_from(f64_to_i64((self as f64).ln())?).ok()
+            }
+        }
+    )*};
+}
+
+impl_elementary_for_float!(f32, f64);
+impl_elementary_for_int!(i32, i64);
+
+/// Conversion to and from f64 for the numerical methods. Integer types only
+/// take back values that are integers up to rounding errors.
+pub trait Real: Sized {
+    fn to_f64(self) -> f64;
+    fn from_f64(x: f64) -> Option<Self>;
+
+    /// Relative rounding error of the arithmetic, the numerical methods can't do better.
+    /// Zero for integer types, their results are exact or an error.
+    fn epsilon() -> f64 {
+        f64::EPSILON
+    }
+}
+
+macro_rules! impl_real_for_float {
+    ($($t:ty),*) => {$(
+        impl Real for $t {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+            fn from_f64(x: f64) -> Option<Self> {
+                Some(x as $t)
+            }
+            fn epsilon() -> f64 {
+                <$t>::EPSILON as f64
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_real_for_int {
+    ($($t:ty),*) => {$(
+        impl Real for $t {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+            fn from_f64(x: f64) -> Option<Self> {
+                let n = x.round();
+                if (x - n).abs() > 1e-9 * n.abs().max(1.0) {
+                    return None;
+                }
+                <$t>::try_from(f64_to_i64(n)?).ok()
+            }
+            fn epsilon() -> f64 {
+                0.0
+            }
+        }
+    )*};
+}
+
+impl_real_for_float!(f32, f64);
+impl_real_for_int!(i32, i64);
+
+/// Values with a physical dimension, only values of the same dimension can be
+/// added, compared or converted into each other. Plain numbers are all dimensionless.
+pub trait Commensurable {
+    fn commensurable(&self, other: &Self) -> bool;
+
+    /// Whether `self op other` is defined. By default anything can be multiplied or divided,
+    /// but only commensurable values added, compared or converted.
+    fn defined(&self, op: Operator, other: &Self) -> bool {
+        match op {
+            Operator::ADD
+            | Operator::SUB
+            | Operator::MOD
+            | Operator::REM
+            | Operator::LT
+            | Operator::LE
+            | Operator::GT
+            | Operator::GE
+            | Operator::IS
+            | Operator::NE
+            | Operator::TO => self.commensurable(other),
+            _ => true,
+        }
+    }
+}
+
+macro_rules! impl_commensurable_for_number {
+    ($($t:ty),*) => {$(
+        impl Commensurable for $t {
+            fn commensurable(&self, _other: &Self) -> bool {
+                true
+            }
+        }
+    )*};
+}
+
+impl_commensurable_for_number!(f32, f64, i32, i64);
+
 pub trait Numeric:
     Copy
     + Add<Output = Self>
@@ -125,9 +715,18 @@ pub trait Numeric:
     + Default
     + Neg<Output = Self>
     + One
+    + Division
+    + Checked
+    + Combinatorics
+    + Elementary
+    + Real
+    + Commensurable
     + FromBinaryStr
     + FromHexStr
+    + FromScientificStr
+    + FromTemporalStr
     + PartialEq
+    + PartialOrd
     + Display
     + Debug
     + FromStr
@@ -143,11 +742,20 @@ impl<T> Numeric for T where
         + Default
         + Neg<Output = T>
         + One
+        + Division
+        + Checked
+        + Combinatorics
+        + Elementary
+        + Real
+        + Commensurable
         + PartialEq
+        + PartialOrd
         + Display
         + Debug
         + FromHexStr
         + FromBinaryStr
+        + FromScientificStr
+        + FromTemporalStr
         + FromStr
 {
 }
@@ -178,4 +786,93 @@ mod tests {
         let hex = f32::from_hex_str(s).unwrap();
         assert_eq!(255.0, hex);
     }
+
+    #[test]
+    fn test_radix_str_to_int() {
+        assert_eq!(-1, i32::from_hex_str("0xffi8").unwrap());
+        assert_eq!(255, i64::from_hex_str("0xff").unwrap());
+        assert_eq!(5, i32::from_binary_str("0b101").unwrap());
+        assert!(i32::from_hex_str("0xffffffffff").is_err());
+    }
+
+    #[test]
+    fn test_scientific_str() {
+        assert_eq!(6.022e23, f64::from_scientific_str("6.022e23").unwrap());
+        assert_eq!(1.5e-3, f32::from_scientific_str("1.5e-3").unwrap());
+        assert_eq!(1000, i32::from_scientific_str("1e3").unwrap());
+        assert_eq!(1500, i64::from_scientific_str("1.500e+3").unwrap());
+        assert_eq!(12, i64::from_scientific_str("1200e-2").unwrap());
+        assert_eq!(0, i64::from_scientific_str("0e999").unwrap());
+        assert!(i64::from_scientific_str("1.5e-3").is_err());
+        assert!(i64::from_scientific_str("6.022e23").is_err());
+        assert!(i32::from_scientific_str("1e10").is_err());
+    }
+
+    #[test]
+    fn test_division_signs() {
+        assert_eq!(Some(-4), (-7i64).floor_div(2));
+        assert_eq!(Some(1), (-7i64).modulo(2));
+        assert_eq!(Some(1), (-7i64).modulo(-2));
+        assert_eq!(Some(-1), (-7i64).remainder(2));
+        assert_eq!(Some(-3), (-7i64).quotient(2));
+        assert_eq!(None, 7i32.modulo(0));
+        assert_eq!(None, i32::MIN.floor_div(-1));
+
+        assert_eq!(Some(-4.0), (-7.0f32).floor_div(2.0));
+        assert_eq!(Some(1.0), (-7.0f32).modulo(2.0));
+        assert_eq!(Some(-1.0), (-7.0f64).remainder(2.0));
+        assert_eq!(None, 1.0f64.quotient(0.0));
+    }
+
+    #[test]
+    fn test_combinatorics() {
+        assert_eq!(Some(2_432_902_008_176_640_000), 20i64.factorial());
+        assert_eq!(None, 21i64.factorial());
+        assert_eq!(None, (-1i32).factorial());
+        assert_eq!(Some(720.0), 6.0f64.factorial());
+        assert!((0.5f64.factorial().unwrap() - 0.886_226_925_452_758).abs() < 1e-12);
+        assert_eq!(None, (-2.0f32).factorial());
+
+        assert_eq!(Some(10), 5i32.combinations(2));
+        assert_eq!(Some(20.0), 5.0f32.permutations(2.0));
+        assert_eq!(Some(6), (-12i64).gcd(18));
+        assert_eq!(Some(36.0), 12.0f64.lcm(18.0));
+        assert_eq!(Some(i64::MAX), i64::MAX.lcm(-1));
+        assert_eq!(None, i64::MIN.lcm(1));
+        assert_eq!(None, 1.5f64.gcd(3.0));
+
+        assert_eq!(Some(true), 97i64.is_prime());
+        assert_eq!(Some(false), 2.5f32.is_prime());
+        assert_eq!(Some(vec![-1, 2, 2, 3]), (-12i32).prime_factors());
+        assert_eq!(Some(vec![2.0, 3.0, 3.0]), 18.0f32.prime_factors());
+    }
+
+    #[test]
+    fn test_elementary() {
+        assert_eq!(Some(1024), 2i64.power(10));
+        assert_eq!(None, 2i32.power(31));
+        assert_eq!(None, 2i64.power(-1));
+        assert_eq!(Some(-1), (-1i32).power(-3));
+        assert_eq!(None, i64::MIN.power(-1));
+        assert_eq!(None, i32::MIN.power(-2));
+        assert_eq!(Some(0.25), 2.0f64.power(-2.0));
+        assert_eq!(None, (-8.0f64).power(1.0 / 3.0));
+
+        assert_eq!(Some(7), 49i64.root());
+        assert_eq!(None, 50i32.root());
+        assert_eq!(Some(1), 0i64.exponential());
+        assert_eq!(None, 1i64.exponential());
+        assert_eq!(Some(0.0), 1.0f32.logarithm());
+        assert_eq!(None, 0.0f64.logarithm());
+        assert!((std::f64::consts::FRAC_PI_6.sine().unwrap() - 0.5).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_real() {
+        assert_eq!(Some(2.5f32), f32::from_f64(2.5));
+        assert_eq!(Some(3i64), i64::from_f64(2.9999999999999));
+        assert_eq!(None, i64::from_f64(2.5));
+        assert_eq!(None, i32::from_f64(1e12));
+        assert_eq!(-4.0, (-4i32).to_f64());
+    }
 }
@@ -1,138 +1,513 @@
-#[derive(Clone, Debug)]
-pub struct BinaryTree<T> {
-    mem: Vec<T>,
-    empty: T,
-}
-
-impl<T> BinaryTree<T>
-where
-    T: Clone + PartialEq,
-{
-    pub fn new(empty: T) -> Self {
-        BinaryTree::<T> {
-            mem: Vec::new(),
-            empty,
+use std::collections::{HashMap, VecDeque};
+
+pub type NodeId = usize;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node<T> {
+    pub value: T,
+    pub children: Vec<NodeId>,
+}
+
+/// Tree with any number of children per node. Nodes live in one arena and refer
+/// to their children by id, so joining subtrees under a new node never copies them.
+///
+/// With the `serde` feature a tree is stored as `{"nodes": [{"value": .., "children": [..]}, ..], "root": ..}`.
+/// Children always come before their parents, which deserializing checks so that no tree has a cycle.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "TreeData<T>")
+)]
+pub struct Tree<T> {
+    nodes: Vec<Node<T>>,
+    root: Option<NodeId>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TreeData<T> {
+    nodes: Vec<Node<T>>,
+    root: Option<NodeId>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<TreeData<T>> for Tree<T> {
+    type Error = String;
+
+    fn try_from(data: TreeData<T>) -> Result<Self, Self::Error> {
+        for (id, node) in data.nodes.iter().enumerate() {
+            if let Some(child) = node.children.iter().find(|&&child| child >= id) {
+                return Err(format!("child {child} of node {id} has to come before it"));
+            }
         }
-    }
-    pub fn new_with_root(x: T, empty: T) -> Self {
-        BinaryTree::<T> {
-            mem: vec![x],
-            empty,
+        if let Some(root) = data.root.filter(|&root| root >= data.nodes.len()) {
+            return Err(format!("root {root} is not a node"));
         }
+        Ok(Tree {
+            nodes: data.nodes,
+            root: data.root,
+        })
     }
-    pub fn bin(&mut self, x: T, b: BinaryTree<T>) -> &BinaryTree<T> {
-        let mut mem = vec![x];
+}
 
-        let new_len = self.mem.len().max(b.mem.len()) * 2 + 1;
+impl<T> Default for Tree<T> {
+    fn default() -> Self {
+        Tree::new()
+    }
+}
 
-        for i in 1..new_len {
-            let mut mask = (i + 1).next_power_of_two();
-            if !(i + 1).is_power_of_two() {
-                mask = mask >> 1;
-            }
-            // println!("i = {}", i);
-            // println!("mask = {:b}", mask);
-
-            if (i + 1) ^ mask >= mask >> 1 {
-                let idx = i ^ mask;
-                match b.mem.get(idx) {
-                    Some(x) => mem.push(x.clone()),
-                    None => mem.push(self.empty.clone()),
-                };
-                // println!("b");
-            } else {
-                // println!("mask >> 1 = {:b}", mask >> 1);
-                // println!("mask & mask = {:b}", mask | (mask >> 1));
-                // println!("i + 1 = {:b}", i + 1);
-                let idx = ((i + 1) ^ (mask | (mask >> 1))) - 1;
-                match self.mem.get(idx) {
-                    Some(x) => mem.push(x.clone()),
-                    None => mem.push(self.empty.clone()),
-                };
-                // println!("self")
-            }
+impl<T> Tree<T> {
+    pub fn new() -> Self {
+        Tree::<T> {
+            nodes: Vec::new(),
+            root: None,
         }
+    }
+
+    pub fn new_with_root(x: T) -> Self {
+        let mut tree = Tree::new();
+        let root = tree.push(x, vec![]);
+        tree.set_root(root);
+        tree
+    }
+
+    /// Adds a node above the existing nodes `children` and returns its id.
+    /// The node only becomes the root through [`Tree::set_root`].
+    pub fn push(&mut self, value: T, children: Vec<NodeId>) -> NodeId {
+        self.nodes.push(Node { value, children });
+        self.nodes.len() - 1
+    }
+
+    pub fn set_root(&mut self, id: NodeId) {
+        self.root = Some(id);
+    }
 
-        self.mem = mem;
-        self
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
     }
 
-    pub fn left_idx(k: usize) -> usize {
-        2 * k + 1
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.nodes.get(id).map(|node| &node.value)
     }
 
-    pub fn right_idx(k: usize) -> usize {
-        2 * k + 2
+    pub fn node(&self, id: NodeId) -> Option<&Node<T>> {
+        self.nodes.get(id)
     }
 
-    pub fn parent_idx(k: usize) -> Option<usize> {
-        if k == 0 {
-            return None;
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        self.nodes
+            .get(id)
+            .map(|node| node.children.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn child(&self, id: NodeId, n: usize) -> Option<NodeId> {
+        self.children(id).get(n).copied()
+    }
+
+    /// Number of nodes in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// All nodes in the arena in insertion order, including nodes that are
+    /// not reachable from the root.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.nodes.iter().map(|node| &node.value).enumerate()
+    }
+
+    /// Iterates the subtree at `k`, each node before its children.
+    pub fn preorder(&self, k: NodeId) -> Preorder<'_, T> {
+        Preorder {
+            tree: self,
+            stack: vec![k],
         }
-        Some((k - 1) / 2)
     }
 
-    pub fn left(&self, k: usize) -> (Option<usize>, Option<&T>) {
-        let idx = BinaryTree::<T>::left_idx(k);
-        match self.mem.get(idx) {
-            Some(node) => match *node == self.empty {
-                true => (None, None),
-                false => (Some(idx), Some(node)),
-            },
-            None => (None, None),
+    /// Iterates the subtree at `k`, the first child, then the node, then the remaining
+    /// children. For binary nodes this is the usual left, node, right order.
+    pub fn inorder(&self, k: NodeId) -> Inorder<'_, T> {
+        Inorder {
+            tree: self,
+            stack: vec![(k, false)],
         }
     }
 
-    pub fn right(&self, k: usize) -> (Option<usize>, Option<&T>) {
-        let idx = BinaryTree::<T>::right_idx(k);
-        match self.mem.get(idx) {
-            Some(node) => match *node == self.empty {
-                true => (None, None),
-                false => (Some(idx), Some(node)),
-            },
-            None => (None, None),
+    /// Iterates the subtree at `k`, each node after its children.
+    pub fn postorder(&self, k: NodeId) -> Postorder<'_, T> {
+        Postorder {
+            tree: self,
+            stack: vec![(k, false)],
         }
     }
 
-    pub fn parent(&self, k: usize) -> (Option<usize>, Option<&T>) {
-        let idx = BinaryTree::<T>::parent_idx(k);
-        match idx {
-            Some(idx) => (Some(idx), self.mem.get(idx)),
-            None => (None, None),
+    /// Iterates the subtree at `k` level by level, each level from left to right.
+    pub fn breadth_first(&self, k: NodeId) -> BreadthFirst<'_, T> {
+        BreadthFirst {
+            tree: self,
+            queue: VecDeque::from([k]),
         }
     }
 
-    pub fn traverse_postorder<F>(&self, k: usize, f: &mut F)
+    /// Visits every node before its children.
+    pub fn traverse_preorder<F>(&self, k: NodeId, f: &mut F)
     where
-        F: FnMut((usize, Option<&T>)),
+        F: FnMut((NodeId, &T)),
     {
-        if k >= self.mem.len() {
-            return;
-        }
-        let node = self.mem.get(k);
-        if node.is_none() || *node.unwrap() == self.empty {
-            return;
-        }
-        self.traverse_postorder(BinaryTree::<T>::left_idx(k), f);
-        self.traverse_postorder(BinaryTree::<T>::right_idx(k), f);
-        f((k, node));
+        self.preorder(k).for_each(f);
     }
 
-    pub fn traverse_inorder<F>(&self, k: usize, f: &mut F)
+    /// Visits the nodes in the order of [`Tree::inorder`].
+    pub fn traverse_inorder<F>(&self, k: NodeId, f: &mut F)
     where
-        F: FnMut((usize, Option<&T>)),
+        F: FnMut((NodeId, &T)),
     {
-        if k >= self.mem.len() {
-            return;
+        self.inorder(k).for_each(f);
+    }
+
+    /// Visits every node after its children.
+    pub fn traverse_postorder<F>(&self, k: NodeId, f: &mut F)
+    where
+        F: FnMut((NodeId, &T)),
+    {
+        self.postorder(k).for_each(f);
+    }
+
+    /// Visits the nodes level by level, each level from left to right.
+    pub fn traverse_level_order<F>(&self, k: NodeId, f: &mut F)
+    where
+        F: FnMut((NodeId, &T)),
+    {
+        self.breadth_first(k).for_each(f);
+    }
+
+    /// Number of edges on the shortest path from the root down to `k`,
+    /// `None` if `k` can't be reached from the root.
+    pub fn depth(&self, k: NodeId) -> Option<usize> {
+        let mut queue = VecDeque::from([(self.root?, 0)]);
+        while let Some((id, depth)) = queue.pop_front() {
+            if id == k {
+                return Some(depth);
+            }
+            queue.extend(self.children(id).iter().map(|&child| (child, depth + 1)));
+        }
+        None
+    }
+
+    /// Number of edges on the longest path from `k` down to a leaf,
+    /// `None` if there is no node at `k`.
+    pub fn height(&self, k: NodeId) -> Option<usize> {
+        self.get(k)?;
+        // Heights of the nodes below `k`, children come before their parents.
+        let mut heights = HashMap::new();
+        for (id, _) in self.postorder(k) {
+            let height = self
+                .children(id)
+                .iter()
+                .map(|child| heights[child] + 1)
+                .max()
+                .unwrap_or(0);
+            heights.insert(id, height);
+        }
+        heights.get(&k).copied()
+    }
+}
+
+impl<T: Clone> Tree<T> {
+    /// Copy of the subtree rooted at `k` without the nodes it can't reach,
+    /// `None` if there is no node at `k`. Shared subtrees stay shared.
+    pub fn subtree(&self, k: NodeId) -> Option<Tree<T>> {
+        self.get(k)?;
+        let mut tree = Tree::new();
+        let root = tree.graft(self, k);
+        tree.set_root(root);
+        Some(tree)
+    }
+
+    /// Copies the subtree of `other` rooted at `k` into this tree and returns the id
+    /// of the copied root. Panics if `other` has no node `k`.
+    pub fn graft(&mut self, other: &Tree<T>, k: NodeId) -> NodeId {
+        // Ids in this tree by id in `other`.
+        let mut ids = HashMap::new();
+        other.traverse_postorder(k, &mut |(id, value)| {
+            if ids.contains_key(&id) {
+                return;
+            }
+            let children = other.children(id).iter().map(|child| ids[child]).collect();
+            ids.insert(id, self.push(value.clone(), children));
+        });
+        ids[&k]
+    }
+}
+
+pub struct Preorder<'a, T> {
+    tree: &'a Tree<T>,
+    stack: Vec<NodeId>,
+}
+
+impl<'a, T> Iterator for Preorder<'a, T> {
+    type Item = (NodeId, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(id) = self.stack.pop() {
+            if let Some(node) = self.tree.nodes.get(id) {
+                self.stack.extend(node.children.iter().rev());
+                return Some((id, &node.value));
+            }
+        }
+        None
+    }
+}
+
+pub struct Inorder<'a, T> {
+    tree: &'a Tree<T>,
+    /// The flag marks nodes whose first child has already been scheduled.
+    stack: Vec<(NodeId, bool)>,
+}
+
+impl<'a, T> Iterator for Inorder<'a, T> {
+    type Item = (NodeId, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((id, expanded)) = self.stack.pop() {
+            let Some(node) = self.tree.nodes.get(id) else {
+                continue;
+            };
+            if expanded {
+                return Some((id, &node.value));
+            }
+            match node.children.split_first() {
+                Some((&first, rest)) => {
+                    self.stack
+                        .extend(rest.iter().rev().map(|&child| (child, false)));
+                    self.stack.push((id, true));
+                    self.stack.push((first, false));
+                }
+                None => return Some((id, &node.value)),
+            }
+        }
+        None
+    }
+}
+
+pub struct Postorder<'a, T> {
+    tree: &'a Tree<T>,
+    /// The flag marks nodes whose children have already been scheduled.
+    stack: Vec<(NodeId, bool)>,
+}
+
+impl<'a, T> Iterator for Postorder<'a, T> {
+    type Item = (NodeId, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((id, expanded)) = self.stack.pop() {
+            let Some(node) = self.tree.nodes.get(id) else {
+                continue;
+            };
+            if expanded {
+                return Some((id, &node.value));
+            }
+            self.stack.push((id, true));
+            self.stack
+                .extend(node.children.iter().rev().map(|&child| (child, false)));
         }
-        let node = self.mem.get(k);
-        if node.is_none() || *node.unwrap() == self.empty {
-            return;
+        None
+    }
+}
+
+pub struct BreadthFirst<'a, T> {
+    tree: &'a Tree<T>,
+    queue: VecDeque<NodeId>,
+}
+
+impl<'a, T> Iterator for BreadthFirst<'a, T> {
+    type Item = (NodeId, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(id) = self.queue.pop_front() {
+            if let Some(node) = self.tree.nodes.get(id) {
+                self.queue.extend(node.children.iter());
+                return Some((id, &node.value));
+            }
         }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_term, token::Token, traits::Constants};
+
+    #[test]
+    fn test_tree_push() {
+        let mut tree = Tree::new();
+        let a = tree.push("a", vec![]);
+        let b = tree.push("b", vec![]);
+        let c = tree.push("c", vec![]);
+        let root = tree.push("f", vec![a, b, c]);
+        tree.set_root(root);
+
+        assert_eq!(4, tree.len());
+        assert_eq!(Some(&"f"), tree.get(root));
+        assert_eq!(Some(c), tree.child(root, 2));
+        assert!(tree.children(a).is_empty());
+
+        let mut order = vec![];
+        tree.traverse_postorder(root, &mut |(_, value)| order.push(*value));
+        assert_eq!(vec!["a", "b", "c", "f"], order);
+    }
+
+    /// `*(+(1, 2), -(3), f(4, 5, 6))`
+    fn sample() -> (Tree<&'static str>, NodeId) {
+        let mut tree = Tree::new();
+        let leaves: Vec<NodeId> = ["1", "2", "3", "4", "5", "6"]
+            .iter()
+            .map(|x| tree.push(*x, vec![]))
+            .collect();
+        let add = tree.push("+", vec![leaves[0], leaves[1]]);
+        let neg = tree.push("-", vec![leaves[2]]);
+        let f = tree.push("f", vec![leaves[3], leaves[4], leaves[5]]);
+        let root = tree.push("*", vec![add, neg, f]);
+        tree.set_root(root);
+        (tree, root)
+    }
+
+    #[test]
+    fn test_traversals() {
+        let (tree, root) = sample();
+        let mut pre = String::new();
+        tree.traverse_preorder(root, &mut |(_, value)| pre += value);
+        let mut inorder = String::new();
+        tree.traverse_inorder(root, &mut |(_, value)| inorder += value);
+        let mut post = String::new();
+        tree.traverse_postorder(root, &mut |(_, value)| post += value);
+        let mut level = String::new();
+        tree.traverse_level_order(root, &mut |(_, value)| level += value);
+
+        assert_eq!("*+12-3f456", pre);
+        assert_eq!("1+2*3-4f56", inorder);
+        assert_eq!("12+3-456f*", post);
+        assert_eq!("*+-f123456", level);
+        assert_eq!(10, tree.iter().count());
+    }
+
+    #[test]
+    fn test_deep_traversal() {
+        let mut tree = Tree::new();
+        let mut id = tree.push(0, vec![]);
+        for i in 1..100_000 {
+            id = tree.push(i, vec![id]);
+        }
+        tree.set_root(id);
+
+        let mut count = 0;
+        tree.traverse_postorder(id, &mut |_| count += 1);
+        assert_eq!(100_000, count);
+    }
+
+    fn parsed(term: &str) -> Tree<Token<f64>> {
+        let constants = Constants::<f64> {
+            e: std::f64::consts::E,
+            pi: std::f64::consts::PI,
+        };
+        parse_term(term, &constants).unwrap()
+    }
+
+    #[test]
+    fn test_iterators() {
+        let tree = parsed("-((1 + 2) * 3)");
+        let root = tree.root().unwrap();
+        let values = |it: &mut dyn Iterator<Item = (NodeId, &Token<f64>)>| {
+            it.map(|(_, token)| token.to_string()).collect::<String>()
+        };
+
+        assert_eq!("-*+123", values(&mut tree.preorder(root)));
+        assert_eq!("1+2*3-", values(&mut tree.inorder(root)));
+        assert_eq!("12+3*-", values(&mut tree.postorder(root)));
+        assert_eq!("-*+312", values(&mut tree.breadth_first(root)));
+
+        let sum = tree.child(tree.child(root, 0).unwrap(), 0).unwrap();
+        assert_eq!("+12", values(&mut tree.preorder(sum)));
+        assert_eq!(0, tree.preorder(tree.len()).count());
+        let sub = tree.subtree(sum).unwrap();
+        assert_eq!("12+", values(&mut sub.postorder(sub.root().unwrap())));
+
+        let tree = parsed(&vec!["1"; 100_000].join(" + "));
+        assert_eq!(199_999, tree.postorder(tree.root().unwrap()).count());
+    }
+
+    #[test]
+    fn test_depth_and_height() {
+        let tree = parsed("-((1 + 2) * 3)");
+        let root = tree.root().unwrap();
+        let product = tree.child(root, 0).unwrap();
+        let sum = tree.child(product, 0).unwrap();
+        let three = tree.child(product, 1).unwrap();
+
+        assert_eq!(Some(0), tree.depth(root));
+        assert_eq!(Some(2), tree.depth(sum));
+        assert_eq!(Some(3), tree.depth(tree.child(sum, 1).unwrap()));
+        assert_eq!(None, tree.depth(tree.len()));
+        assert_eq!(Some(3), tree.height(root));
+        assert_eq!(Some(1), tree.height(sum));
+        assert_eq!(Some(0), tree.height(three));
+        assert_eq!(None, tree.height(tree.len()));
+
+        let tree = parsed("gcd(x, y) ? 1 : 2");
+        assert_eq!(Some(2), tree.height(tree.root().unwrap()));
+    }
+
+    #[test]
+    fn test_tree_subtree() {
+        let mut tree = Tree::new();
+        let unused = tree.push("unused", vec![]);
+        let a = tree.push("a", vec![]);
+        let b = tree.push("b", vec![a]);
+        let c = tree.push("c", vec![b, a]);
+        tree.set_root(c);
+
+        let sub = tree.subtree(b).unwrap();
+        assert_eq!(2, sub.len());
+        assert_eq!(Some(&"b"), sub.get(sub.root().unwrap()));
+        assert_eq!(3, tree.subtree(c).unwrap().len());
+        assert!(tree.subtree(unused + 10).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let constants = Constants::<f64> {
+            e: std::f64::consts::E,
+            pi: std::f64::consts::PI,
+        };
+        let tree = parse_term("-x * 2.5", &constants).unwrap();
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(
+            concat!(
+                r#"{"nodes":[{"value":{"kind":"var","value":"x"},"children":[]},"#,
+                r#"{"value":{"kind":"op","value":"neg"},"children":[0]},"#,
+                r#"{"value":{"kind":"value","value":2.5},"children":[]},"#,
+                r#"{"value":{"kind":"op","value":"mul"},"children":[1,2]}],"root":3}"#
+            ),
+            json
+        );
+        assert_eq!(tree, serde_json::from_str(&json).unwrap());
 
-        self.traverse_inorder(BinaryTree::<T>::left_idx(k), f);
-        f((k, node));
+        let int_constants = Constants::<i64> { e: 2, pi: 3 };
+        let tree = parse_term("x > 0 ? gcd(x, 12) : 7!", &int_constants).unwrap();
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(tree, serde_json::from_str(&json).unwrap());
 
-        self.traverse_inorder(BinaryTree::<T>::right_idx(k), f);
+        let cyclic = r#"{"nodes":[{"value":{"kind":"empty"},"children":[0]}],"root":0}"#;
+        assert!(serde_json::from_str::<Tree<Token<f64>>>(cyclic).is_err());
+        let rootless = r#"{"nodes":[],"root":0}"#;
+        assert!(serde_json::from_str::<Tree<Token<f64>>>(rootless).is_err());
     }
 }
@@ -0,0 +1,436 @@
+//! Gaussian error propagation, every value is a mean with its standard deviation.
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    interval::Endpoint,
+    traits::{
+        Checked, Combinatorics, Commensurable, Constants, Division, Elementary, FromBinaryStr,
+        FromHexStr, FromScientificStr, FromStrError, FromTemporalStr, One, Real,
+    },
+};
+
+/// Slope of `f` at `x` by central differences, for functions without a simple derivative.
+fn slope(f: impl Fn(f64) -> Option<f64>, x: f64) -> Option<f64> {
+    let h = 1e-6 * x.abs().max(1.0);
+    Some((f(x + h)? - f(x - h)?) / (2.0 * h))
+}
+
+/// A measured value `value ± sigma`. Operators and functions propagate the standard deviation
+/// to first order, `sigma(f(a, b))^2 = (df/da * sigma(a))^2 + (df/db * sigma(b))^2`.
+///
+/// Operands are treated as independent, so `x * x` and `x^2` differ in their uncertainty.
+/// Values are ordered by their mean, equal means with different deviations are unordered.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Uncertain<T> {
+    value: T,
+    sigma: T,
+}
+
+impl<T: Endpoint> Uncertain<T> {
+    /// Value with standard deviation `sigma`, `None` if it is negative or NaN.
+    pub fn new(value: T, sigma: T) -> Option<Self> {
+        let comparable = value.partial_cmp(&value).is_some();
+        (comparable && sigma >= T::default()).then_some(Uncertain { value, sigma })
+    }
+
+    /// Value without uncertainty.
+    pub fn exact(value: T) -> Self {
+        Uncertain {
+            value,
+            sigma: T::default(),
+        }
+    }
+
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    pub fn sigma(&self) -> T {
+        self.sigma
+    }
+
+    /// `e` and `pi` without uncertainty.
+    pub fn constants() -> Constants<Self> {
+        Constants {
+            e: Self::exact(T::E),
+            pi: Self::exact(T::PI),
+        }
+    }
+
+    fn is_exact(&self) -> bool {
+        self.sigma == T::default()
+    }
+
+    /// `value` with the deviation propagated from the operands, given with the partial
+    /// derivative by each of them. Exact operands don't contribute even where the
+    /// derivative is infinite.
+    fn propagate(value: T, operands: &[(f64, Self)]) -> Self {
+        let variance: f64 = operands
+            .iter()
+            .filter(|(_, operand)| !operand.is_exact())
+            .map(|(slope, operand)| (slope * operand.sigma.to_f64()).powi(2))
+            .sum();
+        Uncertain {
+            value,
+            sigma: T::from_f64(variance.sqrt()).unwrap_or(T::INFINITY),
+        }
+    }
+
+    /// Result of a function with a finite deviation, `None` otherwise.
+    fn checked(self) -> Option<Self> {
+        (self.sigma < T::INFINITY).then_some(self)
+    }
+
+    /// `f(self)` with the slope `f'` at the value.
+    fn function(self, f: fn(T) -> Option<T>, derivative: f64) -> Option<Self> {
+        Self::propagate(f(self.value)?, &[(derivative, self)]).checked()
+    }
+}
+
+impl<T: Endpoint> Add for Uncertain<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::propagate(self.value + rhs.value, &[(1.0, self), (1.0, rhs)])
+    }
+}
+
+impl<T: Endpoint> Sub for Uncertain<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::propagate(self.value - rhs.value, &[(1.0, self), (-1.0, rhs)])
+    }
+}
+
+impl<T: Endpoint> Neg for Uncertain<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Uncertain {
+            value: -self.value,
+            sigma: self.sigma,
+        }
+    }
+}
+
+impl<T: Endpoint> Mul for Uncertain<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (self.value.to_f64(), rhs.value.to_f64());
+        Self::propagate(self.value * rhs.value, &[(b, self), (a, rhs)])
+    }
+}
+
+impl<T: Endpoint> Div for Uncertain<T> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let (a, b) = (self.value.to_f64(), rhs.value.to_f64());
+        Self::propagate(
+            self.value / rhs.value,
+            &[(1.0 / b, self), (-a / (b * b), rhs)],
+        )
+    }
+}
+
+impl<T: Endpoint> PartialOrd for Uncertain<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.value.partial_cmp(&other.value)? {
+            Ordering::Equal if self.sigma != other.sigma => None,
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl<T: Endpoint> One for Uncertain<T> {
+    fn one() -> Self {
+        Self::exact(T::one())
+    }
+}
+
+/// The integer quotients are exact, the remainders `a - n * b` vary with both operands.
+impl<T: Endpoint> Division for Uncertain<T> {
+    fn quotient(self, rhs: Self) -> Option<Self> {
+        (rhs.value != T::default()).then(|| self / rhs)
+    }
+    fn floor_div(self, rhs: Self) -> Option<Self> {
+        self.value.floor_div(rhs.value).map(Self::exact)
+    }
+    fn modulo(self, rhs: Self) -> Option<Self> {
+        let n = self.value.to_f64().div_euclid(rhs.value.to_f64());
+        let value = self.value.modulo(rhs.value)?;
+        Some(Self::propagate(value, &[(1.0, self), (n, rhs)]))
+    }
+    fn remainder(self, rhs: Self) -> Option<Self> {
+        let n = (self.value.to_f64() / rhs.value.to_f64()).trunc();
+        let value = self.value.remainder(rhs.value)?;
+        Some(Self::propagate(value, &[(1.0, self), (n, rhs)]))
+    }
+}
+
+impl<T: Endpoint> Checked for Uncertain<T> {}
+
+impl<T: Endpoint> FromTemporalStr for Uncertain<T> {}
+
+/// Factorials and binomials are differentiated numerically through the gamma function,
+/// the number theoretic functions only take exact values.
+impl<T: Endpoint> Combinatorics for Uncertain<T> {
+    fn factorial(self) -> Option<Self> {
+        let x = self.value.to_f64();
+        let value = self.value.factorial()?;
+        if self.is_exact() {
+            return Some(Self::exact(value));
+        }
+        let derivative = slope(f64::factorial, x)?;
+        Self::propagate(value, &[(derivative, self)]).checked()
+    }
+    fn permutations(self, k: Self) -> Option<Self> {
+        let (n_value, k_value) = (self.value.to_f64(), k.value.to_f64());
+        let value = self.value.permutations(k.value)?;
+        let by_n = slope(|n| n.permutations(k_value), n_value).unwrap_or(0.0);
+        let by_k = slope(|k| n_value.permutations(k), k_value).unwrap_or(0.0);
+        Self::propagate(value, &[(by_n, self), (by_k, k)]).checked()
+    }
+    fn combinations(self, k: Self) -> Option<Self> {
+        let (n_value, k_value) = (self.value.to_f64(), k.value.to_f64());
+        let value = self.value.combinations(k.value)?;
+        let by_n = slope(|n| n.combinations(k_value), n_value).unwrap_or(0.0);
+        let by_k = slope(|k| n_value.combinations(k), k_value).unwrap_or(0.0);
+        Self::propagate(value, &[(by_n, self), (by_k, k)]).checked()
+    }
+    fn gcd(self, other: Self) -> Option<Self> {
+        if !self.is_exact() || !other.is_exact() {
+            return None;
+        }
+        self.value.gcd(other.value).map(Self::exact)
+    }
+    fn lcm(self, other: Self) -> Option<Self> {
+        if !self.is_exact() || !other.is_exact() {
+            return None;
+        }
+        self.value.lcm(other.value).map(Self::exact)
+    }
+    fn is_prime(&self) -> Option<bool> {
+        if !self.is_exact() {
+            return None;
+        }
+        self.value.is_prime()
+    }
+    fn prime_factors(self) -> Option<Vec<Self>> {
+        if !self.is_exact() {
+            return None;
+        }
+        let factors = self.value.prime_factors()?;
+        Some(factors.into_iter().map(Self::exact).collect())
+    }
+}
+
+impl<T: Endpoint> Elementary for Uncertain<T> {
+    /// `a^b` varies with `b` through `a^b * ln(a)`, so an uncertain exponent needs `a > 0`.
+    fn power(self, exponent: Self) -> Option<Self> {
+        let (a, b) = (self.value.to_f64(), exponent.value.to_f64());
+        let value = self.value.power(exponent.value)?;
+        let by_exponent = match exponent.is_exact() {
+            true => 0.0,
+            false if a > 0.0 => value.to_f64() * a.ln(),
+            false => return None,
+        };
+        let operands = [(b * a.powf(b - 1.0), self), (by_exponent, exponent)];
+        Self::propagate(value, &operands).checked()
+    }
+    fn root(self) -> Option<Self> {
+        let x = self.value.to_f64();
+        self.function(Elementary::root, 0.5 / x.sqrt())
+    }
+    fn sine(self) -> Option<Self> {
+        self.function(Elementary::sine, self.value.to_f64().cos())
+    }
+    fn cosine(self) -> Option<Self> {
+        self.function(Elementary::cosine, -self.value.to_f64().sin())
+    }
+    fn tangent(self) -> Option<Self> {
+        self.function(Elementary::tangent, self.value.to_f64().cos().powi(-2))
+    }
+    fn exponential(self) -> Option<Self> {
+        self.function(Elementary::exponential, self.value.to_f64().exp())
+    }
+    fn logarithm(self) -> Option<Self> {
+        self.function(Elementary::logarithm, 1.0 / self.value.to_f64())
+    }
+}
+
+/// The numerical methods work with the value.
+impl<T: Endpoint> Real for Uncertain<T> {
+    fn to_f64(self) -> f64 {
+        self.value.to_f64()
+    }
+    fn from_f64(x: f64) -> Option<Self> {
+        T::from_f64(x).map(Self::exact)
+    }
+    fn epsilon() -> f64 {
+        T::epsilon()
+    }
+}
+
+impl<T: Endpoint> Commensurable for Uncertain<T> {
+    fn commensurable(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T: Endpoint> FromBinaryStr for Uncertain<T> {
+    fn from_binary_str(s: &str) -> Result<Self, FromStrError> {
+        T::from_binary_str(s).map(Self::exact)
+    }
+}
+
+impl<T: Endpoint> FromHexStr for Uncertain<T> {
+    fn from_hex_str(s: &str) -> Result<Self, FromStrError> {
+        T::from_hex_str(s).map(Self::exact)
+    }
+}
+
+impl<T: Endpoint> FromScientificStr for Uncertain<T> {
+    fn from_scientific_str(s: &str) -> Result<Self, FromStrError> {
+        s.parse()
+    }
+}
+
+/// Reads `9.81±0.02` as a value with its standard deviation, a single number is exact.
+impl<T: Endpoint> FromStr for Uncertain<T> {
+    type Err = FromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |s: &str| T::from_scientific_str(s.trim());
+        let (value, sigma) = match s.split_once('±') {
+            Some((value, sigma)) => (number(value)?, number(sigma)?),
+            None => (number(s)?, T::default()),
+        };
+        Self::new(value, sigma).ok_or_else(|| anyhow!("{} is not a measured value", s).into())
+    }
+}
+
+/// Exact values are shown as they are, others as `value±sigma`.
+impl<T: Endpoint> fmt::Display for Uncertain<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_exact() {
+            true => write!(f, "{}", self.value),
+            false => write!(f, "{}±{}", self.value, self.sigma),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate::{CalculationError, calculate_result},
+        parse::parse_term,
+    };
+
+    fn measured(value: f64, sigma: f64) -> Uncertain<f64> {
+        Uncertain::new(value, sigma).unwrap()
+    }
+
+    fn calculate(term: &str) -> Result<Uncertain<f64>, CalculationError> {
+        calculate_result(&parse_term(term, &Uncertain::constants()).unwrap())
+    }
+
+    fn assert_close(expected: (f64, f64), actual: Uncertain<f64>) {
+        assert!(
+            (expected.0 - actual.value).abs() < 1e-9 && (expected.1 - actual.sigma).abs() < 1e-9,
+            "expected {}±{}, got {actual}",
+            expected.0,
+            expected.1
+        );
+    }
+
+    #[test]
+    fn test_operators() {
+        let (a, b) = (measured(3.0, 0.3), measured(4.0, 0.4));
+        assert_close((7.0, 0.5), a + b);
+        assert_close((-1.0, 0.5), a - b);
+        assert_close((12.0, 1.2 * 2f64.sqrt()), a * b);
+        assert_close((0.75, 0.75 * 0.02_f64.sqrt()), a / b);
+        assert_eq!(measured(-3.0, 0.3), -a);
+        assert_eq!(
+            Uncertain::exact(5.0),
+            Uncertain::exact(2.0) + Uncertain::exact(3.0)
+        );
+
+        assert_eq!(Some(Ordering::Less), a.partial_cmp(&b));
+        assert_eq!(None, a.partial_cmp(&Uncertain::exact(3.0)));
+        assert_close((1.0, 0.3), a.modulo(Uncertain::exact(2.0)).unwrap());
+        assert_close(
+            (1.0, 2.0_f64.hypot(0.3)),
+            measured(7.0, 0.3).remainder(measured(3.0, 1.0)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let x = measured(0.5, 0.01);
+        assert_close((0.5f64.sin(), 0.01 * 0.5f64.cos()), x.sine().unwrap());
+        assert_close(
+            (0.5f64.exp(), 0.01 * 0.5f64.exp()),
+            x.exponential().unwrap(),
+        );
+        assert_close((0.5f64.ln(), 0.02), x.logarithm().unwrap());
+        assert_close((2.0, 0.25), measured(4.0, 1.0).root().unwrap());
+        assert_eq!(None, measured(0.0, 1.0).root());
+        assert_eq!(Some(Uncertain::exact(0.0)), Uncertain::exact(0.0).root());
+
+        assert_close(
+            (8.0, 1.2),
+            measured(2.0, 0.1).power(Uncertain::exact(3.0)).unwrap(),
+        );
+        let power = Uncertain::exact(2.0).power(measured(3.0, 0.1)).unwrap();
+        assert_close((8.0, 0.8 * 2f64.ln()), power);
+        assert_eq!(None, Uncertain::exact(-2.0).power(measured(3.0, 0.1)));
+
+        let factorial = measured(3.0, 0.1).factorial().unwrap();
+        assert!((factorial.sigma - 0.6 * (11.0 / 6.0 - 0.577_215_664_901_532_9)).abs() < 1e-6);
+        assert_eq!(None, measured(6.0, 0.1).gcd(Uncertain::exact(4.0)));
+        assert_eq!(
+            Some(Uncertain::exact(2.0)),
+            Uncertain::exact(6.0).gcd(Uncertain::exact(4.0))
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(measured(9.81, 0.02), "9.81±0.02".parse().unwrap());
+        assert_eq!(measured(1500.0, 20.0), "1.5e3 ± 2e1".parse().unwrap());
+        assert_eq!(Uncertain::exact(2.0), "2".parse().unwrap());
+        assert!("1±-1".parse::<Uncertain<f64>>().is_err());
+        assert!("[1, 2]".parse::<Uncertain<f64>>().is_err());
+
+        assert_eq!("9.81±0.02", measured(9.81, 0.02).to_string());
+        assert_eq!("2", Uncertain::exact(2.0).to_string());
+    }
+
+    #[test]
+    fn test_calculate() {
+        let product = calculate("(9.81±0.02) * (2.0±0.1)").unwrap();
+        assert_close((19.62, (2.0f64 * 0.02).hypot(9.81 * 0.1)), product);
+        let period = calculate("2pi * sqrt((1±0.01) / 9.81)").unwrap();
+        let value = 2.0 * std::f64::consts::PI * (1.0f64 / 9.81).sqrt();
+        assert_close((value, value * 0.005), period);
+        assert_eq!(
+            Err(CalculationError::DivisionByZero),
+            calculate("1 / (0±0.1)")
+        );
+        assert_eq!(Ok(Uncertain::exact(1.0)), calculate("1±0.1 < 2±0.1"));
+    }
+}
@@ -0,0 +1,610 @@
+//! Physical quantities with units, dimension checking and unit conversion.
+
+use std::{
+    cmp::Ordering,
+    f64::consts::PI,
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    calculate::{CalculationError, calculate_result},
+    interval::Endpoint,
+    token::{Operator, Token},
+    traits::{
+        Checked, Combinatorics, Commensurable, Constants, Division, Elementary, FromBinaryStr,
+        FromHexStr, FromScientificStr, FromStrError, FromTemporalStr, Numeric, One, Real,
+    },
+    tree::Tree,
+};
+
+/// Exponents of the SI base units kg, m, s, A, K, mol and cd, followed by money `¤`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dimension([i8; 8]);
+
+const BASE_UNITS: [&str; 8] = ["kg", "m", "s", "A", "K", "mol", "cd", "¤"];
+
+impl Dimension {
+    const NONE: Dimension = Dimension([0; 8]);
+    /// Amounts of money, the [`currency`](crate::currency) module converts between currencies.
+    pub const CURRENCY: Dimension = Dimension([0, 0, 0, 0, 0, 0, 0, 1]);
+
+    const fn of(kg: i8, m: i8, s: i8, a: i8, k: i8, mol: i8, cd: i8) -> Self {
+        Dimension([kg, m, s, a, k, mol, cd, 0])
+    }
+
+    /// Exponent of money, `1` for `¤` and `-1` for `1/¤`.
+    pub(crate) fn currency_exponent(&self) -> i8 {
+        self.0[7]
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    fn zip(self, other: Self, f: impl Fn(i8, i8) -> Option<i8>) -> Option<Self> {
+        let mut exponents = [0; 8];
+        for (i, exponent) in exponents.iter_mut().enumerate() {
+            *exponent = f(self.0[i], other.0[i])?;
+        }
+        Some(Dimension(exponents))
+    }
+
+    /// Dimension of the `n`th power.
+    fn power(self, n: i8) -> Option<Self> {
+        self.zip(Self::NONE, |a, _| a.checked_mul(n))
+    }
+
+    /// Dimension of the square root, if all exponents are even.
+    fn root(self) -> Option<Self> {
+        self.zip(Self::NONE, |a, _| (a % 2 == 0).then_some(a / 2))
+    }
+}
+
+/// Base units with their exponents, `kg m/s^2` or `kg m^2/(s^3 A)`.
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = |positive: bool| {
+            let factors: Vec<String> = (0..BASE_UNITS.len())
+                .filter(|&i| self.0[i] != 0 && (self.0[i] > 0) == positive)
+                .map(|i| match self.0[i].abs() {
+                    1 => BASE_UNITS[i].to_owned(),
+                    n => format!("{}^{}", BASE_UNITS[i], n),
+                })
+                .collect();
+            factors
+        };
+        let (numerator, denominator) = (units(true), units(false));
+        match numerator.is_empty() {
+            true => write!(f, "1")?,
+            false => write!(f, "{}", numerator.join(" "))?,
+        }
+        match denominator.len() {
+            0 => Ok(()),
+            1 => write!(f, "/{}", denominator[0]),
+            _ => write!(f, "/({})", denominator.join(" ")),
+        }
+    }
+}
+
+struct Unit {
+    symbol: &'static str,
+    /// Size in SI base units.
+    factor: f64,
+    dimension: Dimension,
+    /// Whether it takes SI prefixes like `k` or `m`.
+    prefixed: bool,
+}
+
+const fn unit(symbol: &'static str, factor: f64, dimension: Dimension, prefixed: bool) -> Unit {
+    Unit {
+        symbol,
+        factor,
+        dimension,
+        prefixed,
+    }
+}
+
+const MASS: Dimension = Dimension::of(1, 0, 0, 0, 0, 0, 0);
+const LENGTH: Dimension = Dimension::of(0, 1, 0, 0, 0, 0, 0);
+const TIME: Dimension = Dimension::of(0, 0, 1, 0, 0, 0, 0);
+const SPEED: Dimension = Dimension::of(0, 1, -1, 0, 0, 0, 0);
+const PRESSURE: Dimension = Dimension::of(1, -1, -2, 0, 0, 0, 0);
+const ENERGY: Dimension = Dimension::of(1, 2, -2, 0, 0, 0, 0);
+
+/// Units by symbol. Derived units come before the others, results are shown with the
+/// first unit of factor one that has their dimension.
+static UNITS: [Unit; 32] = [
+    unit("m", 1.0, LENGTH, true),
+    // The kilogram takes its prefixes on the gram.
+    unit("g", 1e-3, MASS, true),
+    unit("s", 1.0, TIME, true),
+    unit("A", 1.0, Dimension::of(0, 0, 0, 1, 0, 0, 0), true),
+    unit("K", 1.0, Dimension::of(0, 0, 0, 0, 1, 0, 0), true),
+    unit("mol", 1.0, Dimension::of(0, 0, 0, 0, 0, 1, 0), true),
+    unit("cd", 1.0, Dimension::of(0, 0, 0, 0, 0, 0, 1), true),
+    unit("N", 1.0, Dimension::of(1, 1, -2, 0, 0, 0, 0), true),
+    unit("Pa", 1.0, PRESSURE, true),
+    unit("J", 1.0, ENERGY, true),
+    unit("W", 1.0, Dimension::of(1, 2, -3, 0, 0, 0, 0), true),
+    unit("Hz", 1.0, Dimension::of(0, 0, -1, 0, 0, 0, 0), true),
+    unit("C", 1.0, Dimension::of(0, 0, 1, 1, 0, 0, 0), true),
+    unit("V", 1.0, Dimension::of(1, 2, -3, -1, 0, 0, 0), true),
+    unit("ohm", 1.0, Dimension::of(1, 2, -3, -2, 0, 0, 0), true),
+    unit("F", 1.0, Dimension::of(-1, -2, 4, 2, 0, 0, 0), true),
+    unit("T", 1.0, Dimension::of(1, 0, -2, -1, 0, 0, 0), true),
+    unit("L", 1e-3, Dimension::of(0, 3, 0, 0, 0, 0, 0), true),
+    unit("eV", 1.602_176_634e-19, ENERGY, true),
+    unit("cal", 4.184, ENERGY, true),
+    unit("bar", 1e5, PRESSURE, true),
+    unit("atm", 101_325.0, PRESSURE, false),
+    unit("min", 60.0, TIME, false),
+    unit("h", 3600.0, TIME, false),
+    unit("day", 86_400.0, TIME, false),
+    unit("inch", 0.0254, LENGTH, false),
+    unit("ft", 0.3048, LENGTH, false),
+    unit("mi", 1609.344, LENGTH, false),
+    unit("mph", 1609.344 / 3600.0, SPEED, false),
+    unit("lb", 0.453_592_37, MASS, false),
+    unit("rad", 1.0, Dimension::NONE, false),
+    unit("deg", PI / 180.0, Dimension::NONE, false),
+];
+
+/// SI prefixes with their power of ten, micro is `µ` or `u`.
+const PREFIXES: [(&str, i32); 21] = [
+    ("Y", 24),
+    ("Z", 21),
+    ("E", 18),
+    ("P", 15),
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("h", 2),
+    ("da", 1),
+    ("d", -1),
+    ("c", -2),
+    ("m", -3),
+    ("µ", -6),
+    ("u", -6),
+    ("n", -9),
+    ("p", -12),
+    ("f", -15),
+    ("a", -18),
+    ("z", -21),
+    ("y", -24),
+];
+
+/// Unit and prefix of a symbol like `km`. Units are matched before prefixes,
+/// so `min` is a minute and `mm` a millimetre.
+fn lookup(symbol: &str) -> Option<(&'static str, i32, &'static Unit)> {
+    if let Some(unit) = UNITS.iter().find(|unit| unit.symbol == symbol) {
+        return Some(("", 0, unit));
+    }
+    PREFIXES.iter().find_map(|&(prefix, exponent)| {
+        let rest = symbol.strip_prefix(prefix)?;
+        let unit = UNITS
+            .iter()
+            .find(|unit| unit.prefixed && unit.symbol == rest)?;
+        Some((prefix, exponent, unit))
+    })
+}
+
+/// Whether `word` is the symbol of a unit, possibly with a prefix.
+pub fn is_unit(word: &str) -> bool {
+    lookup(word).is_some()
+}
+
+/// Unit a value was written with, to show it the same way.
+#[derive(Clone, Copy, Debug)]
+struct Symbol {
+    prefix: &'static str,
+    unit: &'static str,
+}
+
+/// A value with a physical dimension. Values are kept in SI base units, `3 km` is `3000 m`,
+/// and only quantities of the same dimension can be added, compared or converted.
+/// A plain zero goes with any dimension, so `x > 0` works for lengths as well.
+/// Units multiply like numbers, `9.81 m/s^2 * 2 kg` is `19.62 N`.
+///
+/// Functions like `sin` and `exp` take dimensionless quantities, `^` a dimensionless
+/// exponent that has to be an integer unless the base is dimensionless too.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quantity<T> {
+    value: T,
+    dimension: Dimension,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    symbol: Option<Symbol>,
+}
+
+impl<T: Endpoint> Quantity<T> {
+    pub fn new(value: T, dimension: Dimension) -> Self {
+        Quantity {
+            value,
+            dimension,
+            symbol: None,
+        }
+    }
+
+    /// Value without a unit.
+    pub fn number(value: T) -> Self {
+        Self::new(value, Dimension::NONE)
+    }
+
+    /// Value in SI base units.
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    /// `e` and `pi` as numbers.
+    pub fn constants() -> Constants<Self> {
+        Constants {
+            e: Self::number(T::E),
+            pi: Self::number(T::PI),
+        }
+    }
+
+    /// One of the unit with `symbol`.
+    fn unit(symbol: &str) -> Option<Self> {
+        let (prefix, exponent, unit) = lookup(symbol)?;
+        // Parsing the decimal rounds the factor only once.
+        let value = T::from_scientific_str(&format!("{}e{}", unit.factor, exponent)).ok()?;
+        Some(Quantity {
+            value,
+            dimension: unit.dimension,
+            symbol: Some(Symbol {
+                prefix,
+                unit: unit.symbol,
+            }),
+        })
+    }
+
+    /// Common dimension for adding or comparing both, a plain zero takes any dimension.
+    fn common_dimension(&self, other: &Self) -> Option<Dimension> {
+        let zero = |q: &Self| q.dimension.is_none() && q.value == T::default();
+        match () {
+            _ if self.dimension == other.dimension || zero(other) => Some(self.dimension),
+            _ if zero(self) => Some(other.dimension),
+            _ => None,
+        }
+    }
+
+    /// Number without a unit, `None` for other quantities.
+    fn dimensionless(self) -> Option<T> {
+        self.dimension.is_none().then_some(self.value)
+    }
+}
+
+impl<T: Endpoint> PartialEq for Quantity<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.dimension == other.dimension
+    }
+}
+
+/// Quantities of different dimensions are unordered.
+impl<T: Endpoint> PartialOrd for Quantity<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.common_dimension(other)?;
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T: Endpoint> Commensurable for Quantity<T> {
+    fn commensurable(&self, other: &Self) -> bool {
+        self.common_dimension(other).is_some()
+    }
+}
+
+/// Sums of different dimensions are NaN, the calculation checks for them first.
+impl<T: Endpoint> Add for Quantity<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        match self.common_dimension(&rhs) {
+            Some(dimension) => Self::new(self.value + rhs.value, dimension),
+            None => Self::new(T::NAN, self.dimension),
+        }
+    }
+}
+
+impl<T: Endpoint> Sub for Quantity<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<T: Endpoint> Neg for Quantity<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.value, self.dimension)
+    }
+}
+
+impl<T: Endpoint> Mul for Quantity<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let dimension = self
+            .dimension
+            .zip(rhs.dimension, |a, b| Some(a.saturating_add(b)));
+        Self::new(self.value * rhs.value, dimension.unwrap_or_default())
+    }
+}
+
+impl<T: Endpoint> Div for Quantity<T> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let dimension = self
+            .dimension
+            .zip(rhs.dimension, |a, b| Some(a.saturating_sub(b)));
+        Self::new(self.value / rhs.value, dimension.unwrap_or_default())
+    }
+}
+
+impl<T: Endpoint> One for Quantity<T> {
+    fn one() -> Self {
+        Self::number(T::one())
+    }
+}
+
+/// Quotients have the dimension of the ratio, remainders that of the dividend.
+impl<T: Endpoint> Division for Quantity<T> {
+    fn quotient(self, rhs: Self) -> Option<Self> {
+        (rhs.value != T::default()).then(|| self / rhs)
+    }
+    fn floor_div(self, rhs: Self) -> Option<Self> {
+        let ratio = self.quotient(rhs)?;
+        Some(Self::new(self.value.floor_div(rhs.value)?, ratio.dimension))
+    }
+    fn modulo(self, rhs: Self) -> Option<Self> {
+        Some(Self::new(self.value.modulo(rhs.value)?, self.dimension))
+    }
+    fn remainder(self, rhs: Self) -> Option<Self> {
+        Some(Self::new(self.value.remainder(rhs.value)?, self.dimension))
+    }
+}
+
+impl<T: Endpoint> Checked for Quantity<T> {}
+
+impl<T: Endpoint> FromTemporalStr for Quantity<T> {}
+
+/// Only numbers without a unit have factorials, divisors or binomials.
+impl<T: Endpoint> Combinatorics for Quantity<T> {
+    fn factorial(self) -> Option<Self> {
+        self.dimensionless()?.factorial().map(Self::number)
+    }
+    fn permutations(self, k: Self) -> Option<Self> {
+        let n = self.dimensionless()?;
+        n.permutations(k.dimensionless()?).map(Self::number)
+    }
+    fn combinations(self, k: Self) -> Option<Self> {
+        let n = self.dimensionless()?;
+        n.combinations(k.dimensionless()?).map(Self::number)
+    }
+    fn gcd(self, other: Self) -> Option<Self> {
+        let a = self.dimensionless()?;
+        a.gcd(other.dimensionless()?).map(Self::number)
+    }
+    fn lcm(self, other: Self) -> Option<Self> {
+        let a = self.dimensionless()?;
+        a.lcm(other.dimensionless()?).map(Self::number)
+    }
+    fn is_prime(&self) -> Option<bool> {
+        self.dimensionless()?.is_prime()
+    }
+    fn prime_factors(self) -> Option<Vec<Self>> {
+        let factors = self.dimensionless()?.prime_factors()?;
+        Some(factors.into_iter().map(Self::number).collect())
+    }
+}
+
+impl<T: Endpoint> Elementary for Quantity<T> {
+    fn power(self, exponent: Self) -> Option<Self> {
+        let n = exponent.dimensionless()?;
+        let dimension = match self.dimension.is_none() {
+            true => Dimension::NONE,
+            false => {
+                let exponent = (n.floor() == n).then(|| n.to_f64())?;
+                self.dimension.power(i8::try_from(exponent as i64).ok()?)?
+            }
+        };
+        Some(Self::new(self.value.power(n)?, dimension))
+    }
+    fn root(self) -> Option<Self> {
+        Some(Self::new(self.value.root()?, self.dimension.root()?))
+    }
+    fn sine(self) -> Option<Self> {
+        self.dimensionless()?.sine().map(Self::number)
+    }
+    fn cosine(self) -> Option<Self> {
+        self.dimensionless()?.cosine().map(Self::number)
+    }
+    fn tangent(self) -> Option<Self> {
+        self.dimensionless()?.tangent().map(Self::number)
+    }
+    fn exponential(self) -> Option<Self> {
+        self.dimensionless()?.exponential().map(Self::number)
+    }
+    fn logarithm(self) -> Option<Self> {
+        self.dimensionless()?.logarithm().map(Self::number)
+    }
+}
+
+/// The numerical methods work with the value in SI base units.
+impl<T: Endpoint> Real for Quantity<T> {
+    fn to_f64(self) -> f64 {
+        self.value.to_f64()
+    }
+    fn from_f64(x: f64) -> Option<Self> {
+        T::from_f64(x).map(Self::number)
+    }
+    fn epsilon() -> f64 {
+        T::epsilon()
+    }
+}
+
+impl<T: Endpoint> FromBinaryStr for Quantity<T> {
+    fn from_binary_str(s: &str) -> Result<Self, FromStrError> {
+        T::from_binary_str(s).map(Self::number)
+    }
+}
+
+impl<T: Endpoint> FromHexStr for Quantity<T> {
+    fn from_hex_str(s: &str) -> Result<Self, FromStrError> {
+        T::from_hex_str(s).map(Self::number)
+    }
+}
+
+impl<T: Endpoint> FromScientificStr for Quantity<T> {
+    fn from_scientific_str(s: &str) -> Result<Self, FromStrError> {
+        T::from_scientific_str(s).map(Self::number)
+    }
+}
+
+/// Reads a unit symbol like `km` as one of that unit and numbers as numbers.
+impl<T: Endpoint> FromStr for Quantity<T> {
+    type Err = FromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(unit) = Self::unit(s) {
+            return Ok(unit);
+        }
+        match s.starts_with(|c: char| c.is_ascii_digit()) {
+            true => T::from_scientific_str(s).map(Self::number),
+            false => Err(anyhow!("{} is not a unit", s).into()),
+        }
+    }
+}
+
+/// Units as they were written, results in SI units like `19.62 N` or `3.5 m/s`.
+impl<T: Endpoint> fmt::Display for Quantity<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(Symbol { prefix, unit }) = self.symbol {
+            return write!(f, "{}{}", prefix, unit);
+        }
+        if self.dimension.is_none() {
+            return write!(f, "{}", self.value);
+        }
+        let derived = UNITS
+            .iter()
+            .find(|unit| unit.factor == 1.0 && unit.dimension == self.dimension);
+        match derived {
+            Some(unit) => write!(f, "{} {}", self.value, unit.symbol),
+            None => write!(f, "{} {}", self.value, self.dimension),
+        }
+    }
+}
+
+/// Value of a conversion like `60 mph to km/h` in the unit it converts to, together with that unit.
+/// Returns `None` if the term isn't a conversion.
+pub fn convert_result<T: Numeric>(
+    term: &Tree<Token<T>>,
+) -> Result<Option<(T, String)>, CalculationError> {
+    let Some(root) = term.root() else {
+        return Ok(None);
+    };
+    let (Some(Token::Op(Operator::TO)), Some(unit)) = (term.get(root), term.child(root, 1)) else {
+        return Ok(None);
+    };
+    let unit = term.subtree(unit).map(|unit| unit.to_string());
+    Ok(Some((calculate_result(term)?, unit.unwrap_or_default())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_term;
+
+    fn calculate(term: &str) -> Result<Quantity<f64>, CalculationError> {
+        calculate_result(&parse_term(term, &Quantity::constants()).unwrap())
+    }
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() <= 1e-12 * expected.abs(),
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_units() {
+        let km = Quantity::<f64>::unit("km").unwrap();
+        assert_eq!(Quantity::new(1000.0, LENGTH), km);
+        assert_eq!("km", km.to_string());
+        assert_eq!(Some(Quantity::new(1e-6, MASS)), Quantity::unit("mg"));
+        assert_eq!(Some(Quantity::new(60.0, TIME)), Quantity::unit("min"));
+        assert_eq!(Some(Quantity::new(1e-3, TIME)), Quantity::unit("ms"));
+        assert_eq!(Some(Quantity::new(1e6, LENGTH)), Quantity::unit("Mm"));
+        assert_eq!(Some(Quantity::new(1e-6, LENGTH)), Quantity::unit("µm"));
+        assert_eq!(None, Quantity::<f64>::unit("kmin"));
+        assert!(is_unit("hPa") && !is_unit("x"));
+
+        assert_eq!("kg m/s^2", Dimension::of(1, 1, -2, 0, 0, 0, 0).to_string());
+        assert_eq!("1/(s A)", Dimension::of(0, 0, -1, -1, 0, 0, 0).to_string());
+        assert_eq!(
+            "19.62 N",
+            Quantity::new(19.62, Dimension::of(1, 1, -2, 0, 0, 0, 0)).to_string()
+        );
+        assert_eq!(
+            "2 m^2/s",
+            Quantity::new(2.0, Dimension::of(0, 2, -1, 0, 0, 0, 0)).to_string()
+        );
+    }
+
+    #[test]
+    fn test_calculate() {
+        assert_eq!(Ok(Quantity::new(3.2, LENGTH)), calculate("3 m + 20 cm"));
+        let force = calculate("9.81 m/s^2 * 2 kg").unwrap();
+        assert_eq!("19.62 N", force.to_string());
+        assert_eq!(Ok(Quantity::number(1.0)), calculate("1 km > 999 m"));
+        assert_eq!(Ok(Quantity::new(3.0, LENGTH)), calculate("sqrt(9 m^2)"));
+        assert_close(1.0, calculate("sin(90 deg)").unwrap().value());
+        assert_eq!(Ok(Quantity::number(1.0)), calculate("2 m / (200 cm)"));
+        assert_eq!(Ok(Quantity::number(1.0)), calculate("-3 s < 0"));
+        assert_eq!(
+            Err(CalculationError::DivisionByZero),
+            calculate("1 m / (0 s)")
+        );
+
+        assert_eq!(
+            Err(CalculationError::IncompatibleUnits(
+                "3 m".to_owned(),
+                "2 s".to_owned()
+            )),
+            calculate("3 m + 2 s")
+        );
+        assert!(calculate("sin(2 m)").is_err());
+        assert!(calculate("sqrt(2 m)").is_err());
+        assert!(calculate("2 m^1.5").is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let convert =
+            |term| convert_result(&parse_term(term, &Quantity::<f64>::constants()).unwrap());
+        let (speed, unit) = convert("60 mph to km/h").unwrap().unwrap();
+        assert_close(96.560_64, speed.value());
+        assert_eq!("km / h", unit);
+        let (length, unit) = convert("1 mi - 1 km in ft").unwrap().unwrap();
+        assert_close((1609.344 - 1000.0) / 0.3048, length.value());
+        assert_eq!("ft", unit);
+        assert!(matches!(
+            convert("3 kg to m"),
+            Err(CalculationError::IncompatibleUnits(_, _))
+        ));
+        assert_eq!(Ok(None), convert("3 m + 2 m"));
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use crate::{
+    sexpr::to_sexpr,
+    token::{Operator, Token},
+    traits::Numeric,
+    tree::{NodeId, Tree},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notation {
+    /// Infix with only the parentheses that precedence requires.
+    Infix,
+    /// Infix with every operation in parentheses.
+    Parenthesized,
+    /// Polish notation, `* + 1 2 3`.
+    Prefix,
+    /// Reverse Polish notation, `1 2 + 3 *`.
+    Postfix,
+    /// S-expression, `(* (+ 1 2) 3)`.
+    SExpression,
+}
+
+/// Precedence of leaves and function calls, they never need parentheses.
+const ATOM: u8 = u8::MAX;
+
+/// How tightly the subexpression at a node holds together. Negative literals
+/// print with a leading `-` and behave like a negation.
+fn precedence<T: Numeric>(token: &Token<T>) -> u8 {
+    match token {
+        Token::Op(op) => op.precedence(),
+        Token::Value(v) if *v < T::default() => Operator::NEG.precedence(),
+        _ => ATOM,
+    }
+}
+
+/// Symbol of a token in Polish notation, where a prefix `-` or `!` can't be
+/// told apart from the binary and postfix ones by position.
+pub(crate) fn polish_symbol<T: Numeric>(token: &Token<T>) -> String {
+    match token {
+        Token::Op(Operator::NEG) => "neg".to_owned(),
+        Token::Op(Operator::NOT) => "not".to_owned(),
+        other => other.to_string(),
+    }
+}
+
+enum Step {
+    Node(NodeId, bool),
+    Text(String),
+}
+
+/// Writes the subexpression at `root` as infix. Works with an explicit stack,
+/// so the depth of the tree is not limited by the call stack.
+fn write_infix<T: Numeric>(tree: &Tree<Token<T>>, root: NodeId, minimal: bool) -> String {
+    let mut out = String::new();
+    let atom = tree.get(root).map(precedence) == Some(ATOM);
+    let mut stack = vec![Step::Node(root, !minimal && !atom)];
+
+    while let Some(step) = stack.pop() {
+        let (id, parenthesize) = match step {
+            Step::Text(text) => {
+                out += &text;
+                continue;
+            }
+            Step::Node(id, parenthesize) => (id, parenthesize),
+        };
+        let Some(token) = tree.get(id) else {
+            continue;
+        };
+        let children = tree.children(id);
+        let p = precedence(token);
+
+        // Decides the parentheses of the n-th child given the lowest precedence
+        // it may have without them.
+        let child = |n: usize, min: u8| {
+            let child = children[n];
+            let child_precedence = tree.get(child).map(precedence).unwrap_or(ATOM);
+            let wrap = match minimal {
+                true => child_precedence < min,
+                false => child_precedence != ATOM,
+            };
+            Step::Node(child, wrap)
+        };
+
+        let mut steps = vec![];
+        if parenthesize {
+            steps.push(Step::Text("(".to_owned()));
+        }
+        match token {
+            Token::Func(func) => {
+                steps.push(Step::Text(format!("{func}(")));
+                for (n, &argument) in children.iter().enumerate() {
+                    if n > 0 {
+                        steps.push(Step::Text(", ".to_owned()));
+                    }
+                    steps.push(Step::Node(argument, false));
+                }
+                steps.push(Step::Text(")".to_owned()));
+            }
+            Token::Op(Operator::COND) if children.len() == 3 => {
+                steps.push(child(0, p + 1));
+                steps.push(Step::Text(" ? ".to_owned()));
+                steps.push(child(1, 0));
+                steps.push(Step::Text(" : ".to_owned()));
+                steps.push(child(2, p));
+            }
+            Token::Op(Operator::FAC) if children.len() == 1 => {
+                steps.push(child(0, p));
+                steps.push(Step::Text(token.to_string()));
+            }
+            Token::Op(op) if op.arity() == 1 && children.len() == 1 => {
+                steps.push(Step::Text(token.to_string()));
+                steps.push(child(0, p));
+            }
+            Token::Op(Operator::POW) if children.len() == 2 => {
+                // `^` groups to the right, so an equally strong left operand needs parentheses.
+                steps.push(child(0, p + 1));
+                steps.push(Step::Text(token.to_string()));
+                steps.push(child(1, p));
+            }
+            Token::Op(_) if children.len() == 2 => {
+                // Binary operators group to the left, so an equally strong right operand
+                // needs parentheses to keep its place in the tree.
+                steps.push(child(0, p));
+                steps.push(Step::Text(format!(" {token} ")));
+                steps.push(child(1, p + 1));
+            }
+            other => steps.push(Step::Text(other.to_string())),
+        }
+        if parenthesize {
+            steps.push(Step::Text(")".to_owned()));
+        }
+
+        stack.extend(steps.into_iter().rev());
+    }
+    out
+}
+
+/// Minimal infix of the subtree at `id`.
+pub(crate) fn infix_at<T: Numeric>(tree: &Tree<Token<T>>, id: NodeId) -> String {
+    write_infix(tree, id, true)
+}
+
+/// Turns a parsed term back into text.
+pub fn unparse<T: Numeric>(tree: &Tree<Token<T>>, notation: Notation) -> String {
+    let Some(root) = tree.root() else {
+        return String::new();
+    };
+
+    match notation {
+        Notation::Infix => write_infix(tree, root, true),
+        Notation::Parenthesized => write_infix(tree, root, false),
+        Notation::Prefix => {
+            let mut symbols = vec![];
+            tree.traverse_preorder(root, &mut |(_, token)| symbols.push(polish_symbol(token)));
+            symbols.join(" ")
+        }
+        Notation::Postfix => {
+            let mut symbols = vec![];
+            tree.traverse_postorder(root, &mut |(_, token)| symbols.push(polish_symbol(token)));
+            symbols.join(" ")
+        }
+        Notation::SExpression => to_sexpr(tree),
+    }
+}
+
+impl<T: Numeric> fmt::Display for Tree<Token<T>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&unparse(self, Notation::Infix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::parse_term, traits::Constants};
+
+    static CONSTANTS: Constants<f64> = Constants::<f64> {
+        e: std::f64::consts::E,
+        pi: std::f64::consts::PI,
+    };
+
+    fn infix(s: &str) -> String {
+        parse_term(s, &CONSTANTS).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        assert_eq!(
+            "(3 + 4) * (17 + 3 * -4 + 5)",
+            infix("((3 + 4) * ((17 + (3 * -4)) + 5)) =")
+        );
+        assert_eq!("1 - (2 - 3) - 4", infix("(1 - (2 - 3)) - 4"));
+        assert_eq!("2 * x / (y * z)", infix("2x / (y z)"));
+        assert_eq!("-(a + b)! + -c!", infix("-((a + b)!) + -(c!)"));
+        assert_eq!("a || b && !(c < d)", infix("a || (b && !(c < d))"));
+        assert_eq!(
+            "(a ? b : c) ? d : u ? v : w",
+            infix("(a ? b : c) ? d : (u ? v : w)")
+        );
+        assert_eq!("gcd(4 + 2, 6) mod 4", infix("gcd((4 + 2), 6) mod 4"));
+        assert_eq!(
+            "(a^b)^c + a^b^c + -a^2",
+            infix("(a^b)^c + a^(b^c) + -(a^2)")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for term in [
+            "1 - (2 - 3) - 4",
+            "-(a + b)! + -c!",
+            "(a ? b : c) ? d : u ? v : w",
+        ] {
+            let printed = infix(term);
+            assert_eq!(printed, infix(&printed));
+        }
+
+        let mut tree = Tree::new();
+        let three = tree.push(Token::Value(-3.0), vec![]);
+        let fac = tree.push(Token::Op(Operator::FAC), vec![three]);
+        tree.set_root(fac);
+        assert_eq!("(-3)!", tree.to_string());
+    }
+
+    #[test]
+    fn test_other_notations() {
+        let tree = parse_term("(1 + 2) * -3 - ncr(5, 2)", &CONSTANTS).unwrap();
+
+        assert_eq!(
+            "(((1 + 2) * (-3)) - nCr(5, 2))",
+            unparse(&tree, Notation::Parenthesized)
+        );
+        assert_eq!("- * + 1 2 neg 3 nCr 5 2", unparse(&tree, Notation::Prefix));
+        assert_eq!("1 2 + 3 neg * 5 2 nCr -", unparse(&tree, Notation::Postfix));
+    }
+}